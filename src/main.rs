@@ -1,72 +1,526 @@
-use clap::{Parser, Subcommand};
-use serde::Serialize;
+use anstyle::{AnsiColor, Color, Style};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use serde::de::{Deserializer, MapAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use hmac::{Hmac, Mac};
 use serde_json::json;
+use sha2::Sha256;
 use std::fs::File;
-use std::io::{self, Write};
-use std::path::Path;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use tera::{Context, Tera};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
 
 // =====================
 //     DATA STRUCTS
 // =====================
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 struct DockerfileSpec {
     base_image: String,
     maintainer: String,
     packages: Vec<String>,
     workdir: String,
     entrypoint: String,
+    /// Default arguments passed to `entrypoint` (`CMD`)
+    cmd: Vec<String>,
+    /// `USER` the final stage runs as; root when unset
+    user: Option<String>,
+    /// In-image path of a generated entrypoint script; `entrypoint` becomes its default CMD
+    entrypoint_script: Option<String>,
+    /// snapshot.debian.org timestamp (`20240101T000000Z`) the package index is pinned to
+    snapshot_date: Option<String>,
+    apt_layers: AptLayers,
+    /// Let `apt-get install` pull in recommended packages (off: `--no-install-recommends`)
+    install_recommends: bool,
+    /// Extra `LABEL`s, e.g. OCI source labels from `--labels-from-git`
+    #[serde(deserialize_with = "deserialize_pairs")]
+    labels: Vec<(String, String)>,
+    /// Standard `org.opencontainers.image.*` labels, written together as one `LABEL`
+    #[serde(deserialize_with = "deserialize_pairs")]
+    oci_labels: Vec<(String, String)>,
+    /// Instructions after `WORKDIR` that copy the project in and build it
+    source_steps: Vec<String>,
+    /// Final stage for a multi-stage build; `base_image` then becomes the `builder` stage
+    runtime: Option<RuntimeStage>,
+    entrypoint_mode: EntrypointMode,
+    /// `FROM --platform=` for every stage
+    platform: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+/// How `ENTRYPOINT`/`CMD` are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum EntrypointMode {
+    /// `ENTRYPOINT ["/bin/bash"]`: runs as PID 1 and receives signals, but the whole value is one
+    /// argument, so `"python app.py"` looks for a program with a space in its name
+    #[default]
+    Exec,
+    /// `ENTRYPOINT /bin/bash`: `/bin/sh -c` splits the arguments, but the shell is PID 1 and
+    /// doesn't forward SIGTERM, so `docker stop` waits out its timeout
+    Shell,
+}
+
+/// The slim stage a multi-stage build ships, holding only the built artifact.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RuntimeStage {
+    base_image: String,
+    /// Path of the build output in the `builder` stage
+    artifact: String,
+    /// Where the artifact is copied to in the runtime image
+    path: String,
+}
+
+/// How `apt-get update` and `apt-get install` are split into image layers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum AptLayers {
+    /// One `RUN`, so the index is never cached apart from the install
+    #[default]
+    Combined,
+    /// Separate `RUN`s, so the update layer is reused while the package list changes
+    Split,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 struct DevContainerSpec {
     name: String,
-    dockerfile_path: String,
+    source: DevContainerSource,
     remote_user: String,
+    /// `source=...,target=...,type=...` mount strings
+    mounts: Vec<String>,
+    /// Extra `docker run` arguments, one element per argv entry
+    run_args: Vec<String>,
+    #[serde(deserialize_with = "deserialize_pairs")]
+    container_env: Vec<(String, String)>,
     customizations: DevContainerCustomizations,
 }
 
-#[derive(Debug, Serialize)]
+/// What the dev container is built from.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum DevContainerSource {
+    /// `build.dockerfile`
+    Dockerfile { path: String },
+    /// `dockerComposeFile` + `service`, reusing a compose stack
+    Compose {
+        /// Merged in order, like `docker compose -f a -f b`
+        files: Vec<String>,
+        service: String,
+        workspace_folder: String,
+    },
+}
+
+impl Default for DevContainerSource {
+    fn default() -> Self {
+        Self::Dockerfile {
+            path: "./Dockerfile".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 struct DevContainerCustomizations {
     vscode_extensions: Vec<String>,
     settings: serde_json::Value,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 struct DockerComposeSpec {
+    /// Top-level `name:`, the compose project name that prefixes containers, networks and volumes
+    project_name: Option<String>,
+    /// Top-level `include:` of other compose files (compose 2.20+)
+    includes: Vec<String>,
     services: Vec<ServiceSpec>,
-    networks: HashMap<String, NetworkConfig>,
+    // BTreeMap keeps rendered output stable across runs (needed for --check)
+    networks: BTreeMap<String, NetworkConfig>,
+    limits_style: LimitsStyle,
+    /// Forced `depends_on` form; `None` picks long only for services with conditions
+    depends_on_style: Option<DependsOnStyle>,
+    /// Top-level `x-*` keys, ignored by Docker but available to tooling
+    x_extensions: BTreeMap<String, serde_json::Value>,
+    /// Environment shared through the `x-common-env` anchor by services with `shared_env`
+    #[serde(deserialize_with = "deserialize_pairs")]
+    common_env: Vec<(String, String)>,
+    /// Precede each service with a `# name: image` comment
+    annotate: bool,
+    /// Top-level `configs` carried inline as `content:`; a spec file gives each as plain text
+    #[serde(deserialize_with = "deserialize_inline_configs")]
+    configs: BTreeMap<String, InlineConfig>,
 }
 
+/// A config file embedded as a YAML block scalar.
 #[derive(Debug, Serialize)]
+struct InlineConfig {
+    /// Block scalar header: `|`, plus an indentation indicator and/or `-` chomping as needed
+    style: String,
+    lines: Vec<String>,
+}
+
+impl InlineConfig {
+    fn new(content: &str) -> Self {
+        let mut style = "|".to_string();
+        // Leading whitespace on the first line would otherwise be read as the block's indentation
+        if content.starts_with([' ', '\t']) {
+            style.push('2');
+        }
+        if !content.ends_with('\n') {
+            style.push('-');
+        }
+        Self {
+            style,
+            // Compose interpolates `${VAR}` in content; `$$` keeps a literal `$`
+            lines: content.lines().map(|line| line.replace('$', "$$")).collect(),
+        }
+    }
+}
+
+/// How `compose --split-by` divides services across files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum SplitBy {
+    /// One file per `--service-group` (ungrouped services go to "app")
+    Group,
+}
+
+/// How `depends_on` is rendered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum DependsOnStyle {
+    /// A plain list of service names (`- db`), understood by older tooling
+    Short,
+    /// A map with a `condition` per dependency
+    Long,
+}
+
+/// Where service resource limits are rendered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum LimitsStyle {
+    /// Under `deploy.resources.limits` (honored by swarm and compose v2)
+    #[default]
+    Deploy,
+    /// As top-level `cpus`/`mem_limit` service keys (plain, non-swarm compose)
+    Inline,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 struct NetworkConfig {
     driver: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 struct ServiceSpec {
     name: String,
     image: String,
     ports: Vec<String>,
-    depends_on: Vec<String>,
+    depends_on: Vec<Dependency>,
+    #[serde(deserialize_with = "deserialize_pairs")]
     environment: Vec<(String, String)>,
+    /// Merge the top-level `x-common-env` anchor into `environment`
+    shared_env: bool,
+    #[serde(deserialize_with = "deserialize_pairs")]
+    labels: Vec<(String, String)>,
     volumes: Vec<String>,
+    entrypoint: Vec<String>,
+    command: Vec<String>,
+    build: Option<BuildConfig>,
+    /// When compose pulls the image: `always`, `never`, `missing` or `build`
+    pull_policy: Option<String>,
+    resources: Option<ResourceLimits>,
+    /// Swarm's `deploy.restart_policy`; plain compose uses `restart` instead
+    restart_policy: Option<RestartPolicy>,
+    read_only: bool,
+    security_opt: Vec<String>,
+    cap_add: Vec<String>,
+    cap_drop: Vec<String>,
+    init: bool,
+    /// Keep stdin open and allocate a TTY (`docker run -it`)
+    stdin_open: bool,
+    tty: bool,
+    user: Option<String>,
+    working_dir: Option<String>,
+    hostname: Option<String>,
+    /// `host`, `none`, `bridge`, `service:<name>` or `container:<name>`
+    network_mode: Option<String>,
+    restart: Option<String>,
+    /// Size of `/dev/shm` (e.g. `1gb`), needed by headless browsers
+    shm_size: Option<String>,
+    /// `develop.watch` rules for `docker compose watch`
+    watch: Vec<WatchRule>,
+    healthcheck: Option<Healthcheck>,
+    x_extensions: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct ResourceLimits {
+    cpus: Option<String>,
+    memory: Option<String>,
+    cpu_shares: Option<u32>,
+    /// Soft guarantees under `deploy.resources.reservations`
+    reservations: ResourceReservations,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct ResourceReservations {
+    cpus: Option<String>,
+    memory: Option<String>,
+}
+
+/// `deploy.restart_policy`: how swarm restarts a service's failed tasks.
+#[derive(Debug, Serialize, Deserialize)]
+struct RestartPolicy {
+    /// `none`, `on-failure` or `any`
+    condition: String,
+    max_attempts: Option<u32>,
+    /// How long a restart must stay up to count as successful
+    window: Option<String>,
+    /// Wait between restart attempts
+    delay: Option<String>,
+}
+
+/// A `develop.watch` rule: what `docker compose watch` does when files under `path` change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WatchRule {
+    /// `sync`, `rebuild` or `sync+restart`
+    action: String,
+    path: String,
+    /// Container path synced to; required by the sync actions
+    target: Option<String>,
+}
+
+/// A `depends_on` entry. Any condition switches the service to the long (map) form.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Dependency {
+    service: String,
+    #[serde(default)]
+    condition: Option<String>,
+    /// Restart the dependent when this dependency restarts (compose 2.20+, long form only)
+    #[serde(default)]
+    restart: bool,
+}
+
+impl Dependency {
+    fn new(service: &str) -> Self {
+        Self {
+            service: service.to_string(),
+            condition: None,
+            restart: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Healthcheck {
+    test: Vec<String>,
+    interval: String,
+    timeout: String,
+    retries: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BuildConfig {
+    context: String,
+    #[serde(default)]
+    dockerfile: Option<String>,
+    /// Multi-stage build stage to stop at
+    #[serde(default)]
+    target: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_pairs")]
+    args: Vec<(String, String)>,
+}
+
+impl BuildConfig {
+    fn new(context: &str) -> Self {
+        Self {
+            context: context.to_string(),
+            dockerfile: None,
+            target: None,
+            args: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct EntrypointScriptSpec {
+    name: String,
+    language: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ProxySpec {
+    /// Service the proxy forwards to
+    app: String,
+    /// Container port the app listens on
+    port: String,
 }
 
 #[derive(Debug, Serialize)]
+struct VscodeTasksSpec {
+    /// Passed to `docker compose` as `-f` in order
+    compose_files: Vec<String>,
+    /// Task label and the `docker compose` arguments it runs
+    tasks: Vec<(String, Vec<String>)>,
+}
+
+#[derive(Debug, Serialize)]
+struct ReadmeSpec {
+    name: String,
+    language: String,
+    database: Option<String>,
+    services: Vec<String>,
+    app_port: String,
+    /// Reverse proxy the app is reached through instead of `app_port`
+    proxy: Option<String>,
+    /// Host port the proxy publishes, after `--port-offset`
+    proxy_port: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 struct DockerBakeSpec {
+    #[serde(default = "default_bake_group")]
     group_name: String,
     targets: Vec<BakeTarget>,
+    #[serde(default, deserialize_with = "deserialize_pairs")]
+    variables: Vec<(String, String)>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 struct BakeTarget {
     name: String,
     context: String,
     dockerfile: String,
     tags: Vec<String>,
+    no_cache: bool,
+    pull: bool,
+    outputs: Vec<String>,
+    /// `id=...,env=...` or `id=...,src=...` build secrets
+    secrets: Vec<String>,
+    annotations: Vec<String>,
+    #[serde(deserialize_with = "deserialize_pairs")]
+    labels: Vec<(String, String)>,
+    platforms: Vec<String>,
+    /// Dockerfile embedded as `dockerfile-inline`, used instead of `dockerfile`
+    #[serde(deserialize_with = "deserialize_heredoc")]
+    dockerfile_inline: Option<HclHeredoc>,
+}
+
+/// Text rendered as an HCL heredoc, with a delimiter that doesn't occur as a line of the text.
+#[derive(Debug, Serialize)]
+struct HclHeredoc {
+    delimiter: String,
+    /// The text with HCL's `${`/`%{` template sequences escaped, ending in a newline
+    body: String,
+}
+
+impl HclHeredoc {
+    fn new(content: &str) -> Self {
+        let mut delimiter = "EOT".to_string();
+        let mut n = 1;
+        while content.lines().any(|line| line.trim() == delimiter) {
+            delimiter = format!("EOT{}", n);
+            n += 1;
+        }
+        let mut body = content.replace("${", "$${").replace("%{", "%%{");
+        if !body.ends_with('\n') {
+            body.push('\n');
+        }
+        Self { delimiter, body }
+    }
+}
+
+fn deserialize_heredoc<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<HclHeredoc>, D::Error> {
+    Ok(Option::<String>::deserialize(deserializer)?.map(|content| HclHeredoc::new(&content)))
+}
+
+fn default_bake_group() -> String {
+    "default".to_string()
+}
+
+/// A Nomad job converted from a compose spec: one group whose tasks are the compose services.
+#[derive(Debug, Serialize)]
+struct NomadJobSpec {
+    job: String,
+    datacenter: String,
+    ports: Vec<NomadPort>,
+    tasks: Vec<NomadTask>,
+}
+
+/// A port in the group's `network` block. Without `static_port` Nomad picks the host port.
+#[derive(Debug, Serialize)]
+struct NomadPort {
+    label: String,
+    static_port: Option<u16>,
+    to: u16,
+}
+
+/// A docker-driver task for one compose service.
+#[derive(Debug, Serialize)]
+struct NomadTask {
+    name: String,
+    image: String,
+    /// Labels of the group ports the task publishes
+    ports: Vec<String>,
+    entrypoint: Vec<String>,
+    command: Option<String>,
+    args: Vec<String>,
+    env: Vec<(String, String)>,
+}
+
+/// Everything `project --from-file` generates; each section present is rendered and written.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProjectSpec {
+    dockerfile: Option<DockerfileSpec>,
+    compose: Option<DockerComposeSpec>,
+    devcontainer: Option<DevContainerSpec>,
+    bake: Option<DockerBakeSpec>,
+}
+
+/// Reads key/value pairs written either as a mapping (`KEY: value`) or as a list of
+/// `[key, value]` pairs. Scalar values are kept as their YAML text, so `PORT: 8000` works.
+fn deserialize_pairs<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<(String, String)>, D::Error> {
+    use serde::de::Error;
+    fn scalar(value: &serde_yaml::Value) -> Option<String> {
+        match value {
+            serde_yaml::Value::String(s) => Some(s.clone()),
+            serde_yaml::Value::Number(n) => Some(n.to_string()),
+            serde_yaml::Value::Bool(b) => Some(b.to_string()),
+            serde_yaml::Value::Null => Some(String::new()),
+            _ => None,
+        }
+    }
+    match serde_yaml::Value::deserialize(deserializer)? {
+        serde_yaml::Value::Null => Ok(Vec::new()),
+        serde_yaml::Value::Mapping(map) => map
+            .iter()
+            .map(|(k, v)| scalar(k).zip(scalar(v)).ok_or_else(|| D::Error::custom("expected scalar keys and values")))
+            .collect(),
+        serde_yaml::Value::Sequence(items) => items
+            .iter()
+            .map(|item| match item.as_sequence().map(Vec::as_slice) {
+                Some([k, v]) => scalar(k).zip(scalar(v)).ok_or_else(|| D::Error::custom("expected scalar keys and values")),
+                _ => Err(D::Error::custom("expected [key, value] pairs")),
+            })
+            .collect(),
+        _ => Err(D::Error::custom("expected a mapping or a list of [key, value] pairs")),
+    }
+}
+
+/// Reads inline configs as `name: content`, the same text `--config-inline` takes.
+fn deserialize_inline_configs<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<String, InlineConfig>, D::Error> {
+    let configs = BTreeMap::<String, String>::deserialize(deserializer)?;
+    Ok(configs.into_iter().map(|(name, content)| (name, InlineConfig::new(&content))).collect())
 }
 
 // =====================
@@ -75,23 +529,130 @@ struct BakeTarget {
 
 static DOCKERFILE_TEMPLATE: &str = r#"
 # Generated Dockerfile
-FROM {{ base_image }}
+FROM {% if platform %}--platform={{ platform }} {% endif %}{{ base_image }}{% if runtime %} AS builder{% endif %}
+{%- if not runtime %}
 LABEL maintainer="{{ maintainer }}"
-RUN apt-get update && apt-get install -y \
+{%- for label in labels %}
+LABEL {{ label.0 }}="{{ label.1 }}"
+{%- endfor %}
+{%- if oci_labels | length > 0 %}
+LABEL {% for label in oci_labels %}{{ label.0 }}="{{ label.1 }}"{% if not loop.last %} \
+      {% endif %}{% endfor %}
+{%- endif %}
+{%- endif %}
+{%- if packages | length > 0 %}
+{%- set update = "apt-get update" %}
+{%- if snapshot_date %}
+{%- set update = "apt-get -o Acquire::Check-Valid-Until=false update" %}
+RUN for f in /etc/apt/sources.list.d/debian.sources /etc/apt/sources.list; do \
+        if [ -f "$f" ]; then sed -i \
+            -e 's|http://deb.debian.org/debian-security|http://snapshot.debian.org/archive/debian-security/{{ snapshot_date }}|g' \
+            -e 's|http://deb.debian.org/debian|http://snapshot.debian.org/archive/debian/{{ snapshot_date }}|g' "$f"; fi; \
+    done
+{%- endif %}
+{%- if apt_layers == "split" %}
+RUN {{ update }}
+RUN apt-get install -y{% if not install_recommends %} --no-install-recommends{% endif %} \
+{%- else %}
+RUN {{ update }} && apt-get install -y{% if not install_recommends %} --no-install-recommends{% endif %} \
+{%- endif %}
 {%- for pkg in packages %}
-    {{ pkg }} \
+    {{ pkg }}{% if not loop.last %} \{% endif %}
+{%- endfor %}
+{%- endif %}
+WORKDIR {{ workdir }}
+{%- for step in source_steps %}
+{{ step }}
 {%- endfor %}
+{%- if runtime %}
+
+FROM {% if platform %}--platform={{ platform }} {% endif %}{{ runtime.base_image }}
+LABEL maintainer="{{ maintainer }}"
+{%- for label in labels %}
+LABEL {{ label.0 }}="{{ label.1 }}"
+{%- endfor %}
+{%- if oci_labels | length > 0 %}
+LABEL {% for label in oci_labels %}{{ label.0 }}="{{ label.1 }}"{% if not loop.last %} \
+      {% endif %}{% endfor %}
+{%- endif %}
 WORKDIR {{ workdir }}
+COPY --from=builder {{ runtime.artifact }} {{ runtime.path }}
+{%- endif %}
+{%- if entrypoint_script %}
+COPY entrypoint.sh {{ entrypoint_script }}
+RUN chmod +x {{ entrypoint_script }}
+{%- endif %}
+{%- if user %}
+USER {{ user }}
+{%- endif %}
+{%- if entrypoint_script %}
+ENTRYPOINT ["{{ entrypoint_script }}"]
+{%- if entrypoint_mode == "shell" %}
+CMD {{ entrypoint }}{% for arg in cmd %} {{ arg }}{% endfor %}
+{%- else %}
+CMD ["{{ entrypoint }}"{% for arg in cmd %}, {{ arg | json_encode }}{% endfor %}]
+{%- endif %}
+{%- elif entrypoint_mode == "shell" %}
+ENTRYPOINT {{ entrypoint }}{% for arg in cmd %} {{ arg }}{% endfor %}
+{%- else %}
 ENTRYPOINT ["{{ entrypoint }}"]
+{%- if cmd | length > 0 %}
+CMD {{ cmd | json_encode }}
+{%- endif %}
+{%- endif %}
+"#;
+
+static ENTRYPOINT_SH_TEMPLATE: &str = r#"#!/bin/sh
+# Generated entrypoint for {{ name }}: runs startup tasks, then hands off to CMD.
+set -e
+
+# Fail fast when required settings are missing, e.g.:
+# : "${SECRET_KEY:?SECRET_KEY must be set}"
+
+# Startup tasks (migrations, cache warmup, ...) go here, e.g.:
+{%- if language == "python" %}
+# python manage.py migrate --noinput
+{%- elif language == "node" %}
+# npm run migrate
+{%- elif language == "rust" %}
+# sqlx migrate run
+{%- else %}
+# ./scripts/migrate.sh
+{%- endif %}
+
+exec "$@"
 "#;
 
 static DEVCONTAINER_TEMPLATE: &str = r#"
 {
-    "name": "{{ name }}",
+    "name": {{ name | json_encode }},
+    {%- if source.kind == "compose" %}
+    {%- if source.files | length == 1 %}
+    "dockerComposeFile": {{ source.files.0 | json_encode }},
+    {%- else %}
+    "dockerComposeFile": {{ source.files | json_encode }},
+    {%- endif %}
+    "service": {{ source.service | json_encode }},
+    "workspaceFolder": {{ source.workspace_folder | json_encode }},
+    {%- else %}
     "build": {
-        "dockerfile": "{{ dockerfile_path }}"
+        "dockerfile": {{ source.path | json_encode }}
+    },
+    {%- endif %}
+    "remoteUser": {{ remote_user | json_encode }},
+    {%- if mounts | length > 0 %}
+    "mounts": {{ mounts | json_encode }},
+    {%- endif %}
+    {%- if run_args | length > 0 %}
+    "runArgs": {{ run_args | json_encode }},
+    {%- endif %}
+    {%- if container_env | length > 0 %}
+    "containerEnv": {
+        {%- for env in container_env %}
+        {{ env.0 | json_encode }}: {{ env.1 | json_encode }}{% if not loop.last %},{% endif %}
+        {%- endfor %}
     },
-    "remoteUser": "{{ remote_user }}",
+    {%- endif %}
     "customizations": {
         "vscode": {
             "extensions": {{ customizations.vscode_extensions | json_encode }},
@@ -102,31 +663,228 @@ static DEVCONTAINER_TEMPLATE: &str = r#"
 "#;
 
 static DOCKER_COMPOSE_TEMPLATE: &str = r#"
+# Generated compose file
 version: '3.8'
+{%- if project_name %}
+name: {{ project_name }}
+{%- endif %}
+{%- if includes | length > 0 %}
+include:
+{%- for path in includes %}
+  - {{ path }}
+{%- endfor %}
+{%- endif %}
+{%- for key, value in x_extensions %}
+{{ key }}: {{ value | json_encode }}
+{%- endfor %}
+{%- if common_env | length > 0 %}
+x-common-env: &common-env
+{%- for env in common_env %}
+  {{ env.0 }}: "{{ env.1 }}"
+{%- endfor %}
+{%- endif %}
 services:
 {%- for service in services %}
+  {%- if annotate %}
+  # {{ service.name }}: {% if service.image %}{{ service.image }}{% elif service.build %}built from {{ service.build.context }}{% if service.build.dockerfile %} ({{ service.build.dockerfile }}){% endif %}{% endif %}
+  {%- endif %}
   {{ service.name }}:
+    {%- for key, value in service.x_extensions %}
+    {{ key }}: {{ value | json_encode }}
+    {%- endfor %}
+    {%- if service.image %}
     image: {{ service.image }}
+    {%- endif %}
+    {%- if service.build %}
+    build:
+      context: {{ service.build.context }}
+      {%- if service.build.dockerfile %}
+      dockerfile: {{ service.build.dockerfile }}
+      {%- endif %}
+      {%- if service.build.target %}
+      target: {{ service.build.target }}
+      {%- endif %}
+      {%- if service.build.args | length > 0 %}
+      args:
+        {%- for arg in service.build.args %}
+        {{ arg.0 }}: "{{ arg.1 }}"
+        {%- endfor %}
+      {%- endif %}
+    {%- endif %}
+    {%- if service.pull_policy %}
+    pull_policy: {{ service.pull_policy }}
+    {%- endif %}
+    {%- if service.entrypoint | length > 0 %}
+    entrypoint: {{ service.entrypoint | json_encode }}
+    {%- endif %}
+    {%- if service.command | length > 0 %}
+    command: {{ service.command | json_encode }}
+    {%- endif %}
+    {%- if service.ports | length > 0 %}
     ports:
     {%- for port in service.ports %}
       - "{{ port }}"
     {%- endfor %}
+    {%- endif %}
+    {%- if service.restart %}
+    restart: {{ service.restart }}
+    {%- endif %}
     {%- if service.depends_on | length > 0 %}
     depends_on:
+    {%- if depends_on_style == "long" or depends_on_style != "short" and service.depends_on | map(attribute="condition") | join(sep="") %}
     {%- for dep in service.depends_on %}
-      - {{ dep }}
+      {{ dep.service }}:
+        condition: {{ dep.condition | default(value="service_started") }}
+        {%- if dep.restart %}
+        restart: true
+        {%- endif %}
     {%- endfor %}
+    {%- else %}
+    {%- for dep in service.depends_on %}
+      - {{ dep.service }}
+    {%- endfor %}
+    {%- endif %}
+    {%- endif %}
+    {%- if service.healthcheck %}
+    healthcheck:
+      test: {{ service.healthcheck.test | json_encode }}
+      interval: {{ service.healthcheck.interval }}
+      timeout: {{ service.healthcheck.timeout }}
+      retries: {{ service.healthcheck.retries }}
     {%- endif %}
-    {%- if service.environment | length > 0 %}
+    {%- if service.shared_env or service.environment | length > 0 %}
     environment:
+    {%- if service.shared_env %}
+      <<: *common-env
+    {%- endif %}
     {%- for env in service.environment %}
       {{ env.0 }}: "{{ env.1 }}"
     {%- endfor %}
     {%- endif %}
+    {%- if service.labels | length > 0 %}
+    labels:
+    {%- for label in service.labels %}
+      {{ label.0 }}: "{{ label.1 }}"
+    {%- endfor %}
+    {%- endif %}
+    {%- if service.volumes | length > 0 %}
     volumes:
     {%- for volume in service.volumes %}
       - {{ volume }}
     {%- endfor %}
+    {%- endif %}
+    {%- if service.user %}
+    user: "{{ service.user }}"
+    {%- endif %}
+    {%- if service.working_dir %}
+    working_dir: {{ service.working_dir }}
+    {%- endif %}
+    {%- if service.hostname %}
+    hostname: {{ service.hostname }}
+    {%- endif %}
+    {%- if service.network_mode %}
+    network_mode: "{{ service.network_mode }}"
+    {%- endif %}
+    {%- if service.init %}
+    init: true
+    {%- endif %}
+    {%- if service.shm_size %}
+    shm_size: {{ service.shm_size }}
+    {%- endif %}
+    {%- if service.stdin_open %}
+    stdin_open: true
+    {%- endif %}
+    {%- if service.tty %}
+    tty: true
+    {%- endif %}
+    {%- if service.read_only %}
+    read_only: true
+    {%- endif %}
+    {%- if service.security_opt | length > 0 %}
+    security_opt:
+    {%- for opt in service.security_opt %}
+      - {{ opt }}
+    {%- endfor %}
+    {%- endif %}
+    {%- if service.cap_add | length > 0 %}
+    cap_add:
+    {%- for cap in service.cap_add %}
+      - {{ cap }}
+    {%- endfor %}
+    {%- endif %}
+    {%- if service.cap_drop | length > 0 %}
+    cap_drop:
+    {%- for cap in service.cap_drop %}
+      - {{ cap }}
+    {%- endfor %}
+    {%- endif %}
+    {%- if service.resources %}
+    {%- if service.resources.cpu_shares %}
+    cpu_shares: {{ service.resources.cpu_shares }}
+    {%- endif %}
+    {%- if limits_style == "inline" %}
+    {%- if service.resources.cpus %}
+    cpus: {{ service.resources.cpus }}
+    {%- endif %}
+    {%- if service.resources.memory %}
+    mem_limit: {{ service.resources.memory }}
+    {%- endif %}
+    {%- if service.resources.reservations.memory %}
+    mem_reservation: {{ service.resources.reservations.memory }}
+    {%- endif %}
+    {%- endif %}
+    {%- endif %}
+    {%- set deploy_limits = service.resources and limits_style != "inline" and (service.resources.cpus or service.resources.memory) %}
+    {#- A CPU reservation has no top-level key, so it stays under deploy in the inline style too #}
+    {%- set deploy_reservations = service.resources and (service.resources.reservations.cpus or (limits_style != "inline" and service.resources.reservations.memory)) %}
+    {%- if deploy_limits or deploy_reservations or service.restart_policy %}
+    deploy:
+      {%- if deploy_limits or deploy_reservations %}
+      resources:
+        {%- if deploy_limits %}
+        limits:
+          {%- if service.resources.cpus %}
+          cpus: "{{ service.resources.cpus }}"
+          {%- endif %}
+          {%- if service.resources.memory %}
+          memory: {{ service.resources.memory }}
+          {%- endif %}
+        {%- endif %}
+        {%- if deploy_reservations %}
+        reservations:
+          {%- if service.resources.reservations.cpus %}
+          cpus: "{{ service.resources.reservations.cpus }}"
+          {%- endif %}
+          {%- if service.resources.reservations.memory and limits_style != "inline" %}
+          memory: {{ service.resources.reservations.memory }}
+          {%- endif %}
+        {%- endif %}
+      {%- endif %}
+      {%- if service.restart_policy %}
+      restart_policy:
+        condition: {{ service.restart_policy.condition }}
+        {%- if service.restart_policy.delay %}
+        delay: {{ service.restart_policy.delay }}
+        {%- endif %}
+        {%- if service.restart_policy.max_attempts %}
+        max_attempts: {{ service.restart_policy.max_attempts }}
+        {%- endif %}
+        {%- if service.restart_policy.window %}
+        window: {{ service.restart_policy.window }}
+        {%- endif %}
+      {%- endif %}
+    {%- endif %}
+    {%- if service.watch | length > 0 %}
+    develop:
+      watch:
+      {%- for rule in service.watch %}
+        - action: {{ rule.action }}
+          path: {{ rule.path }}
+          {%- if rule.target %}
+          target: {{ rule.target }}
+          {%- endif %}
+      {%- endfor %}
+    {%- endif %}
 {%- endfor %}
 
 {%- if networks | length > 0 %}
@@ -136,13 +894,30 @@ networks:
     driver: {{ config.driver }}
 {%- endfor %}
 {%- endif %}
+{%- if configs | length > 0 %}
+configs:
+{%- for name, config in configs %}
+  {{ name }}:
+    content: {{ config.style }}
+{%- for line in config.lines %}
+{% if line %}      {{ line }}{% endif %}
+{%- endfor %}
+{%- endfor %}
+{%- endif %}
 "#;
 
 static DOCKER_BAKE_TEMPLATE: &str = r#"
+# Generated bake file
+{% for v in variables -%}
+variable "{{ v.0 }}" {
+  default = "{{ v.1 }}"
+}
+
+{% endfor -%}
 group "{{ group_name }}" {
   targets = [
 {%- for t in targets %}
-    "{{ t.name }}",
+    "{{ t.name }}"{% if not loop.last %},{% endif %}
 {%- endfor %}
   ]
 }
@@ -150,595 +925,5954 @@ group "{{ group_name }}" {
 {%- for t in targets %}
 target "{{ t.name }}" {
   context    = "{{ t.context }}"
+  {%- if t.dockerfile_inline %}
+  dockerfile-inline = <<{{ t.dockerfile_inline.delimiter }}
+{{ t.dockerfile_inline.body }}{{ t.dockerfile_inline.delimiter }}
+  {%- else %}
   dockerfile = "{{ t.dockerfile }}"
+  {%- endif %}
   tags       = [
     {%- for tag in t.tags %}
-    "{{ tag }}",
+    "{{ tag }}"{% if not loop.last %},{% endif %}
+    {%- endfor %}
+  ]
+  {%- if t.no_cache %}
+  no-cache   = true
+  {%- endif %}
+  {%- if t.pull %}
+  pull       = true
+  {%- endif %}
+  {%- if t.platforms | length > 0 %}
+  platforms  = [
+    {%- for p in t.platforms %}
+    "{{ p }}"{% if not loop.last %},{% endif %}
+    {%- endfor %}
+  ]
+  {%- endif %}
+  {%- if t.outputs | length > 0 %}
+  output     = [
+    {%- for o in t.outputs %}
+    "{{ o }}"{% if not loop.last %},{% endif %}
+    {%- endfor %}
+  ]
+  {%- endif %}
+  {%- if t.secrets | length > 0 %}
+  secret     = [
+    {%- for s in t.secrets %}
+    "{{ s }}"{% if not loop.last %},{% endif %}
+    {%- endfor %}
+  ]
+  {%- endif %}
+  {%- if t.labels | length > 0 %}
+  labels = {
+    {%- for label in t.labels %}
+    "{{ label.0 }}" = "{{ label.1 }}"
+    {%- endfor %}
+  }
+  {%- endif %}
+  {%- if t.annotations | length > 0 %}
+  annotations = [
+    {%- for a in t.annotations %}
+    "{{ a }}"{% if not loop.last %},{% endif %}
     {%- endfor %}
   ]
+  {%- endif %}
 }
 {%- endfor %}
 "#;
 
-// =====================
-//   TEMPLATE RENDER
-// =====================
+static NOMAD_TEMPLATE: &str = r#"# Generated Nomad job
+job "{{ job }}" {
+  datacenters = ["{{ datacenter }}"]
+  type        = "service"
 
-fn render_template<T: Serialize>(template_str: &str, data: &T) -> Result<String, tera::Error> {
-    let mut tera = Tera::default();
-    tera.add_raw_template("dynamic_template", template_str)?;
-    let context = Context::from_serialize(data)?;
-    tera.render("dynamic_template", &context)
+  group "{{ job }}" {
+    {%- if ports | length > 0 %}
+    network {
+      {%- for port in ports %}
+      port "{{ port.label }}" {
+        {%- if port.static_port %}
+        static = {{ port.static_port }}
+        {%- endif %}
+        to     = {{ port.to }}
+      }
+      {%- endfor %}
+    }
+    {%- endif %}
+    {%- for task in tasks %}
+
+    task "{{ task.name }}" {
+      driver = "docker"
+
+      config {
+        image = "{{ task.image }}"
+        {%- if task.ports | length > 0 %}
+        ports = [{% for port in task.ports %}"{{ port }}"{% if not loop.last %}, {% endif %}{% endfor %}]
+        {%- endif %}
+        {%- if task.entrypoint | length > 0 %}
+        entrypoint = [{% for arg in task.entrypoint %}"{{ arg }}"{% if not loop.last %}, {% endif %}{% endfor %}]
+        {%- endif %}
+        {%- if task.command %}
+        command = "{{ task.command }}"
+        {%- endif %}
+        {%- if task.args | length > 0 %}
+        args = [{% for arg in task.args %}"{{ arg }}"{% if not loop.last %}, {% endif %}{% endfor %}]
+        {%- endif %}
+      }
+      {%- if task.env | length > 0 %}
+
+      env {
+        {%- for env in task.env %}
+        {{ env.0 }} = "{{ env.1 }}"
+        {%- endfor %}
+      }
+      {%- endif %}
+    }
+    {%- endfor %}
+  }
 }
+"#;
 
-fn write_to_file(output_path: &Path, contents: &str) -> std::io::Result<()> {
-    let mut file = File::create(output_path)?;
-    file.write_all(contents.as_bytes())?;
-    println!("Wrote file to: {}", output_path.display());
-    Ok(())
+static README_TEMPLATE: &str = r#"# {{ name }}
+
+Development environment scaffolded with configgen.
+
+## Stack
+
+- Language: {{ language }}
+{%- if database %}
+- Database: {{ database }}
+{%- endif %}
+{%- if services | length > 0 %}
+- Services: {{ services | join(sep=", ") }}
+{%- endif %}
+
+## Usage
+
+Build the app image:
+
+```bash
+docker compose build
+```
+
+Start the stack (add `-d` to run in the background):
+
+```bash
+docker compose up
+```
+
+{% if proxy -%}
+The app is reached through {{ proxy }} on http://{% if proxy == "traefik" %}{{ name }}.{% endif %}localhost:{{ proxy_port }}.
+{%- else -%}
+The app is published on `{{ app_port }}` (host:container).
+{%- endif %}
+
+Stop and remove the containers:
+
+```bash
+docker compose down
+```
+"#;
+
+static VSCODE_TASKS_TEMPLATE: &str = r#"// Generated VS Code tasks: build, start and stop the compose stack.
+{
+    "version": "2.0.0",
+    "tasks": [
+        {%- for task in tasks %}
+        {
+            "label": "compose: {{ task.0 }}",
+            "type": "shell",
+            "command": "docker",
+            "args": ["compose"{% for file in compose_files %}, "-f", {{ file | json_encode }}{% endfor %}{% for arg in task.1 %}, {{ arg | json_encode }}{% endfor %}],
+            "problemMatcher": []{% if task.0 == "build" %},
+            "group": {"kind": "build", "isDefault": true}{% endif %}
+        }{% if not loop.last %},{% endif %}
+        {%- endfor %}
+    ]
 }
+"#;
 
-// =====================
-//     CLI COMMANDS
-// =====================
+/// Written next to the compose file by `--wait-for`. Blocks until every
+/// `host:port` argument accepts TCP connections, then execs the command after `--`.
+static WAIT_FOR_IT_SCRIPT: &str = r#"#!/usr/bin/env bash
+# Generated wait script: waits for each host:port, then runs the given command.
+set -e
 
-#[derive(Parser)]
-#[command(
-    name = "configgen",
-    version = "0.1.0",
-    about = "Generates Docker/OCI-related config files in pure Rust!"
-)]
-struct Cli {
-    #[command(subcommand)]
-    command: Commands,
+timeout="${WAIT_FOR_TIMEOUT:-60}"
+
+while [ "$#" -gt 0 ] && [ "$1" != "--" ]; do
+    host="${1%:*}"
+    port="${1##*:}"
+    elapsed=0
+    until (echo > "/dev/tcp/$host/$port") >/dev/null 2>&1; do
+        if [ "$elapsed" -ge "$timeout" ]; then
+            echo "wait-for-it: timed out after ${timeout}s waiting for $host:$port" >&2
+            exit 1
+        fi
+        sleep 1
+        elapsed=$((elapsed + 1))
+    done
+    echo "wait-for-it: $host:$port is available"
+    shift
+done
+
+[ "$1" = "--" ] && shift
+exec "$@"
+"#;
+
+static TRAEFIK_TEMPLATE: &str = r#"# Generated proxy config: Traefik routes to containers by their traefik.* labels.
+entryPoints:
+  web:
+    address: ":80"
+providers:
+  docker:
+    exposedByDefault: false
+"#;
+
+static NGINX_TEMPLATE: &str = r#"# Generated proxy config: forwards every request to {{ app }}.
+server {
+    listen 80;
+
+    location / {
+        proxy_pass http://{{ app }}:{{ port }};
+        proxy_set_header Host $host;
+        proxy_set_header X-Real-IP $remote_addr;
+        proxy_set_header X-Forwarded-For $proxy_add_x_forwarded_for;
+        proxy_set_header X-Forwarded-Proto $scheme;
+    }
 }
+"#;
 
-#[derive(Subcommand)]
-enum Commands {
-    /// Generate only a Dockerfile
-    Dockerfile {
-        /// Base image to use
-        #[arg(long, default_value = "ubuntu:22.04")]
-        base_image: String,
-        /// Name/email of maintainer
-        #[arg(long, default_value = "Jane Doe <jane@example.com>")]
-        maintainer: String,
-        /// Comma-separated list of packages
-        #[arg(long, default_value = "curl,git")]
-        packages: String,
-        /// Working directory in container
-        #[arg(long, default_value = "/app")]
-        workdir: String,
-        /// Entrypoint
-        #[arg(long, default_value = "/bin/bash")]
-        entrypoint: String,
-        /// Output filename
-        #[arg(short, long, default_value = "Dockerfile")]
-        output: String,
-    },
-    /// Generate a docker-compose.yml with customizable services
-    Compose {
-        /// Output filename
-        #[arg(short, long, default_value = "docker-compose.yml")]
-        output: String,
-        /// Comma-separated list of services to include (e.g., nginx,postgres,redis)
-        #[arg(long)]
-        services: Option<String>,
-        /// Comma-separated list of ports for each service (e.g., "80:80,5432:5432,6379:6379")
-        #[arg(long)]
-        ports: Option<String>,
-        /// Comma-separated list of volumes (e.g., "./data:/var/lib/postgresql/data")
-        #[arg(long)]
-        volumes: Option<String>,
-        /// Comma-separated list of environment variables (e.g., "POSTGRES_USER=admin,POSTGRES_PASSWORD=secret")
-        #[arg(long)]
-        env: Option<String>,
-        /// Comma-separated list of networks to create (defaults to bridge driver)
-        #[arg(long, default_value = "app_network")]
-        networks: String,
-        /// Comma-separated list of service dependencies (e.g., "web:db,cache:db")
-        #[arg(long)]
-        depends_on: Option<String>,
-    },
-    /// Generate a docker-bake.hcl with customizable targets
-    Bake {
-        /// Output filename
-        #[arg(short, long, default_value = "docker-bake.hcl")]
-        output: String,
-        /// Group name for the targets
-        #[arg(long, default_value = "default")]
-        group: String,
-        /// Comma-separated list of target names (e.g., "api,worker,scheduler")
-        #[arg(long)]
-        targets: Option<String>,
-        /// Comma-separated list of contexts for each target (e.g., "./api,./worker,./scheduler")
-        #[arg(long)]
-        contexts: Option<String>,
-        /// Comma-separated list of Dockerfile paths (e.g., "./api/Dockerfile,./worker/Dockerfile")
-        #[arg(long)]
-        dockerfiles: Option<String>,
-        /// Comma-separated list of tags for each target (e.g., "api:latest,worker:latest")
-        #[arg(long)]
-        tags: Option<String>,
-    },
-    /// Generate a development container configuration
-    Devcontainer {
-        /// Container name
-        #[arg(long, default_value = "Dev Container")]
-        name: String,
-        /// Dockerfile path
-        #[arg(long, default_value = "./Dockerfile")]
-        dockerfile: String,
-        /// Remote user name
-        #[arg(long, default_value = "vscode")]
-        remote_user: String,
-        /// Comma-separated VSCode extensions
-        #[arg(long, default_value = "ms-azuretools.vscode-docker,rust-lang.rust-analyzer")]
-        extensions: String,
-        /// Output filename
-        #[arg(short, long, default_value = "devcontainer.json")]
-        output: String,
-    },
-    /// Generate a complete development environment
-    Init {
-        /// Project name
-        #[arg(long, default_value = "myproject")]
-        name: String,
-        /// Programming language/framework (e.g., python, node, rust)
-        #[arg(long, default_value = "python")]
-        language: String,
-        /// Database type (e.g., postgres, mysql, mongodb)
-        #[arg(long)]
-        database: Option<String>,
-        /// Additional services (comma-separated, e.g., redis,elasticsearch)
-        #[arg(long)]
-        services: Option<String>,
-        /// Output directory
-        #[arg(short, long, default_value = ".")]
-        output_dir: String,
-    },
+static CADDYFILE_TEMPLATE: &str = r#"# Generated proxy config: forwards every request to {{ app }}.
+:80 {
+	reverse_proxy {{ app }}:{{ port }}
 }
+"#;
 
-fn prompt(message: &str) -> io::Result<String> {
-    print!("{}: ", message);
-    io::stdout().flush()?;
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
-    Ok(input.trim().to_string())
+/// Where the wait script is mounted inside the app container.
+const WAIT_FOR_IT_PATH: &str = "/usr/local/bin/wait-for-it.sh";
+
+/// A built-in template, under the name `render_template` is called with.
+struct TemplateEntry {
+    name: &'static str,
+    description: &'static str,
+    source: &'static str,
 }
 
-fn select_option(options: &[&str], prompt_msg: &str) -> io::Result<usize> {
-    println!("\n{}", prompt_msg);
-    for (i, opt) in options.iter().enumerate() {
-        println!("{}. {}", i + 1, opt);
+/// Every built-in template, listed by `templates list` and printed by `templates show`.
+static TEMPLATES: &[TemplateEntry] = &[
+    TemplateEntry { name: "Dockerfile", description: "Dockerfile (DockerfileSpec)", source: DOCKERFILE_TEMPLATE },
+    TemplateEntry { name: "docker-compose.yml", description: "Compose file (DockerComposeSpec)", source: DOCKER_COMPOSE_TEMPLATE },
+    TemplateEntry { name: "docker-bake.hcl", description: "Buildx bake file (DockerBakeSpec)", source: DOCKER_BAKE_TEMPLATE },
+    TemplateEntry { name: "job.nomad.hcl", description: "Nomad job converted from a compose spec (NomadJobSpec)", source: NOMAD_TEMPLATE },
+    TemplateEntry { name: "devcontainer.json", description: "Dev container config (DevContainerSpec)", source: DEVCONTAINER_TEMPLATE },
+    TemplateEntry { name: "README.md", description: "Project README from init --readme (ReadmeSpec)", source: README_TEMPLATE },
+    TemplateEntry { name: "entrypoint.sh", description: "Startup script from init --entrypoint-script (EntrypointScriptSpec)", source: ENTRYPOINT_SH_TEMPLATE },
+    TemplateEntry { name: "traefik.yml", description: "Traefik static config from init --proxy traefik (ProxySpec)", source: TRAEFIK_TEMPLATE },
+    TemplateEntry { name: "nginx.conf", description: "nginx site from init --proxy nginx (ProxySpec)", source: NGINX_TEMPLATE },
+    TemplateEntry { name: "Caddyfile", description: "Caddy config from init --proxy caddy (ProxySpec)", source: CADDYFILE_TEMPLATE },
+    TemplateEntry { name: "tasks.json", description: "VS Code tasks from init --vscode-tasks (VscodeTasksSpec)", source: VSCODE_TASKS_TEMPLATE },
+    TemplateEntry { name: "wait-for-it.sh", description: "Dependency wait script from init --wait-for (static, not rendered)", source: WAIT_FOR_IT_SCRIPT },
+];
+
+/// A file `init` may write, with a check recognising the generator's own output so
+/// `clean` never removes a user-written file that happens to share the name.
+struct GeneratedFile {
+    name: &'static str,
+    is_generated: fn(&str) -> bool,
+}
+
+/// Every file `init` can produce. `init` resolves its output paths through this list
+/// and `clean` removes from it, so the two stay in sync.
+const GENERATED_FILES: &[GeneratedFile] = &[
+    GeneratedFile { name: "Dockerfile", is_generated: |c| c.contains("# Generated Dockerfile") },
+    GeneratedFile { name: "entrypoint.sh", is_generated: |c| c.contains("# Generated entrypoint") },
+    GeneratedFile { name: "wait-for-it.sh", is_generated: |c| c.contains("# Generated wait script") },
+    GeneratedFile { name: "compose.yaml", is_generated: |c| c.contains("# Generated compose file") },
+    GeneratedFile { name: "docker-compose.yml", is_generated: |c| c.contains("# Generated compose file") },
+    GeneratedFile { name: "compose.override.yaml", is_generated: |c| c.contains("# Generated compose file") },
+    GeneratedFile { name: "docker-compose.override.yml", is_generated: |c| c.contains("# Generated compose file") },
+    GeneratedFile { name: "docker-bake.hcl", is_generated: |c| c.contains("# Generated bake file") },
+    GeneratedFile { name: "devcontainer.json", is_generated: is_generated_devcontainer },
+    GeneratedFile { name: DEVCONTAINER_COMPOSE_FILE, is_generated: |c| c.contains("# Generated compose file") },
+    GeneratedFile { name: ".env", is_generated: |c| c.contains("# Generated env file") },
+    GeneratedFile { name: ".env.example", is_generated: |c| c.contains("# Generated env example") },
+    GeneratedFile { name: "traefik.yml", is_generated: |c| c.contains("# Generated proxy config") },
+    GeneratedFile { name: "nginx.conf", is_generated: |c| c.contains("# Generated proxy config") },
+    GeneratedFile { name: "Caddyfile", is_generated: |c| c.contains("# Generated proxy config") },
+    GeneratedFile { name: "README.md", is_generated: |c| c.contains("scaffolded with configgen") },
+    GeneratedFile { name: VSCODE_TASKS_FILE, is_generated: |c| c.contains("// Generated VS Code tasks") },
+];
+
+/// Tasks file `init --vscode-tasks` writes for the compose stack.
+const VSCODE_TASKS_FILE: &str = ".vscode/tasks.json";
+
+/// Compose file `init --devcontainer-compose` adds to keep the app container running.
+const DEVCONTAINER_COMPOSE_FILE: &str = ".devcontainer/docker-compose.yml";
+
+/// Top-level keys `DEVCONTAINER_TEMPLATE` can emit.
+const DEVCONTAINER_KEYS: &[&str] = &[
+    "name", "build", "dockerComposeFile", "service", "workspaceFolder", "remoteUser", "mounts", "runArgs", "containerEnv", "customizations",
+];
+
+/// JSON can't carry a marker comment, so require the keys the template always emits
+/// and nothing it never does.
+fn is_generated_devcontainer(contents: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(contents)
+        .ok()
+        .and_then(|v| v.as_object().cloned())
+        .is_some_and(|o| {
+            ["name", "remoteUser", "customizations"].iter().all(|k| o.contains_key(*k))
+                && o.keys().all(|k| DEVCONTAINER_KEYS.contains(&k.as_str()))
+        })
+}
+
+/// The `GENERATED_FILES` entry for `name`.
+fn generated_file(name: &str) -> &'static GeneratedFile {
+    GENERATED_FILES
+        .iter()
+        .find(|f| f.name == name)
+        .unwrap_or_else(|| panic!("{} missing from GENERATED_FILES", name))
+}
+
+/// Path of one of the `GENERATED_FILES` inside `dir`.
+fn generated_path(dir: &str, name: &str) -> PathBuf {
+    debug_assert!(GENERATED_FILES.iter().any(|f| f.name == name), "{} missing from GENERATED_FILES", name);
+    Path::new(dir).join(name)
+}
+
+// =====================
+//       LOGGING
+// =====================
+
+/// Set once from `--verbose`; read by the `verbose!` macro.
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+
+/// Prints a diagnostic line to stderr when `--verbose` is set.
+macro_rules! verbose {
+    ($($arg:tt)*) => {
+        if VERBOSE.load(Ordering::Relaxed) {
+            eprint!("[verbose] ");
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+/// Set once from `--strict`; read by the `warning!` macro.
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+/// Number of `warning!`s raised during this run.
+static WARNINGS: AtomicUsize = AtomicUsize::new(0);
+
+/// Reports a problem with the generated output. Under `--strict` it is printed as an
+/// error, none of the queued files are written and the run exits non-zero.
+macro_rules! warning {
+    ($($arg:tt)*) => {{
+        WARNINGS.fetch_add(1, Ordering::Relaxed);
+        let level = if STRICT.load(Ordering::Relaxed) { "error" } else { "warning" };
+        eprintln!("{}: {}", level, format_args!($($arg)*));
+    }};
+}
+
+// =====================
+//   TEMPLATE RENDER
+// =====================
+
+/// Renders `template_str` with `data`. `name` identifies the template in
+/// verbose output and error messages.
+fn render_template<T: Serialize>(name: &str, template_str: &str, data: &T) -> Result<String, tera::Error> {
+    verbose!("using built-in template '{}'", name);
+    if VERBOSE.load(Ordering::Relaxed) {
+        let spec = serde_json::to_string_pretty(data).unwrap_or_default();
+        verbose!("resolved spec:\n{}", spec);
     }
-    
-    loop {
-        let input = prompt("Enter number")?;
-        if let Ok(num) = input.parse::<usize>() {
-            if num > 0 && num <= options.len() {
-                return Ok(num - 1);
-            }
+
+    let mut tera = Tera::default();
+    tera.add_raw_template(name, template_str)?;
+    let context = Context::from_serialize(data)?;
+    let rendered = tera.render(name, &context)?;
+    Ok(rendered)
+}
+
+/// What to do when a generated file already exists on disk.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+enum OverwritePolicy {
+    /// Leave the existing file untouched
+    Skip,
+    /// Replace the existing file (with a warning)
+    #[default]
+    Overwrite,
+    /// Rename the existing file to `<name>.bak`, then write
+    Backup,
+}
+
+/// Which default filename to use for generated compose files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+enum FilenameConvention {
+    /// `compose.yaml`, preferred by Docker Compose v2
+    #[default]
+    Modern,
+    /// `docker-compose.yml`
+    Legacy,
+}
+
+impl FilenameConvention {
+    fn compose_filename(self) -> &'static str {
+        match self {
+            Self::Modern => "compose.yaml",
+            Self::Legacy => "docker-compose.yml",
+        }
+    }
+
+    /// The override filename `docker compose` merges automatically.
+    fn override_filename(self) -> &'static str {
+        match self {
+            Self::Modern => "compose.override.yaml",
+            Self::Legacy => "docker-compose.override.yml",
         }
-        println!("Please enter a number between 1 and {}", options.len());
     }
 }
 
-fn confirm(message: &str) -> io::Result<bool> {
-    loop {
-        let input = prompt(&format!("{} (y/n)", message))?.to_lowercase();
-        match input.as_str() {
-            "y" | "yes" => return Ok(true),
-            "n" | "no" => return Ok(false),
-            _ => println!("Please enter 'y' or 'n'"),
+/// Reverse proxy `init --proxy` puts in front of the app.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Proxy {
+    /// Routes by labels on the app service, discovered through the Docker socket
+    Traefik,
+    Nginx,
+    Caddy,
+}
+
+impl Proxy {
+    fn image(self) -> &'static str {
+        match self {
+            Self::Traefik => "traefik:v3.1",
+            Self::Nginx => "nginx:latest",
+            Self::Caddy => "caddy:latest",
+        }
+    }
+
+    /// The generated config file, its template and where it is mounted in the proxy container.
+    fn config(self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            Self::Traefik => ("traefik.yml", TRAEFIK_TEMPLATE, "/etc/traefik/traefik.yml"),
+            Self::Nginx => ("nginx.conf", NGINX_TEMPLATE, "/etc/nginx/conf.d/default.conf"),
+            Self::Caddy => ("Caddyfile", CADDYFILE_TEMPLATE, "/etc/caddy/Caddyfile"),
         }
     }
 }
 
-fn interactive_cli() -> io::Result<()> {
-    println!("\n=== Docker Configuration Generator ===\n");
-    
-    let options = ["Generate Dockerfile", "Generate Docker Compose", "Generate Dev Container", "Generate Docker Bake", "Generate Complete Environment"];
-    let choice = select_option(&options, "What would you like to generate?")?;
+/// Serialization of the generated compose files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+enum ComposeFormat {
+    #[default]
+    Yaml,
+    /// Plain JSON, which compose also accepts (anchors and merge keys are resolved)
+    Json,
+}
 
-    match choice {
-        0 => {
-            // Dockerfile
-            let base_image = prompt("Base image (default: ubuntu:22.04)")?;
-            let base_image = if base_image.is_empty() { "ubuntu:22.04".to_string() } else { base_image };
-            
-            let maintainer = prompt("Maintainer (default: Generated <generated@example.com>)")?;
-            let maintainer = if maintainer.is_empty() { "Generated <generated@example.com>".to_string() } else { maintainer };
-            
-            let packages = prompt("Packages (comma-separated, default: curl,git)")?;
-            let packages = if packages.is_empty() { "curl,git".to_string() } else { packages };
-            
-            let workdir = prompt("Working directory (default: /app)")?;
-            let workdir = if workdir.is_empty() { "/app".to_string() } else { workdir };
-            
-            let entrypoint = prompt("Entrypoint (default: /bin/bash)")?;
-            let entrypoint = if entrypoint.is_empty() { "/bin/bash".to_string() } else { entrypoint };
-            
-            let output = prompt("Output filename (default: Dockerfile)")?;
-            let output = if output.is_empty() { "Dockerfile".to_string() } else { output };
+impl ComposeFormat {
+    /// Swaps the extension of a default filename to match the format.
+    fn filename(self, name: &str) -> String {
+        match self {
+            Self::Yaml => name.to_string(),
+            Self::Json => Path::new(name).with_extension("json").display().to_string(),
+        }
+    }
 
-            let spec = DockerfileSpec {
-                base_image,
-                maintainer,
-                packages: packages.split(',').map(|s| s.trim().to_string()).collect(),
-                workdir,
-                entrypoint,
-            };
-            let rendered = render_template(DOCKERFILE_TEMPLATE, &spec).expect("Failed to render Dockerfile");
-            write_to_file(Path::new(&output), &rendered)?;
+    fn render(self, spec: &DockerComposeSpec) -> io::Result<String> {
+        let yaml = render_template("docker-compose.yml", DOCKER_COMPOSE_TEMPLATE, spec).expect("Failed to render docker-compose.yml");
+        match self {
+            Self::Yaml => Ok(yaml),
+            Self::Json => {
+                // One template for both formats, so they can't drift apart
+                let mut value: serde_yaml::Value = serde_yaml::from_str(&yaml)
+                    .map_err(|e| invalid_input(format!("generated compose file is not valid YAML: {}", e)))?;
+                value
+                    .apply_merge()
+                    .map_err(|e| invalid_input(format!("cannot resolve compose merge keys: {}", e)))?;
+                let json = serde_json::to_string_pretty(&value)
+                    .map_err(|e| invalid_input(format!("cannot convert compose file to JSON: {}", e)))?;
+                Ok(json + "\n")
+            }
         }
-        1 => {
-            // Docker Compose
-            let mut services = Vec::new();
-            loop {
-                println!("\n=== Add Service ===");
-                let name = prompt("Service name")?;
-                let image = prompt("Image (default: latest)")?;
-                let image = if image.is_empty() { format!("{}:latest", name) } else { image };
-                
-                let ports = prompt("Ports (comma-separated, e.g., 80:80,443:443)")?;
-                let ports: Vec<String> = if ports.is_empty() {
-                    vec!["80:80".to_string()]
-                } else {
-                    ports.split(',').map(|s| s.trim().to_string()).collect()
-                };
+    }
+}
 
-                let volumes = prompt("Volumes (comma-separated, e.g., ./data:/data)")?;
-                let volumes: Vec<String> = if volumes.is_empty() {
-                    vec!["./data:/data".to_string()]
-                } else {
-                    volumes.split(',').map(|s| s.trim().to_string()).collect()
-                };
+/// Compose settings that `--dev-only` moves from the base file into the override file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum DevOnlySetting {
+    /// Published ports
+    Ports,
+    /// Bind mounts (named volumes stay in the base file)
+    Mounts,
+}
+
+/// Deployment environment `--environment` tailors the generated services for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Environment {
+    /// Bind-mount the source into services built from it and publish debug ports
+    Dev,
+    /// Run the built images as-is, restart them always and leave out dev-only services
+    Prod,
+}
+
+/// Output settings shared by every command's writes.
+#[derive(Clone, Copy, Debug, Default)]
+struct OutputOptions {
+    policy: OverwritePolicy,
+    /// Compare against the files on disk instead of writing them (`--check`)
+    check: bool,
+    /// Check every generated Dockerfile for common issues (`--lint`)
+    lint: bool,
+    /// Collect files for `--archive` instead of writing them
+    archive: bool,
+    /// Check every assembled compose spec for structural problems (`--compose-lint`)
+    compose_lint: bool,
+    /// Render everything but only report what would be written (`--dry-run`)
+    dry_run: bool,
+    /// With `dry_run`, show a diff against each file on disk (`--diff`)
+    diff: bool,
+}
+
+impl OutputOptions {
+    /// Whether files (and the directories holding them) are written to disk this run.
+    fn writes_files(self) -> bool {
+        !self.check && !self.archive && !self.dry_run
+    }
+}
+
+/// Set once from `--post-format`; every file is piped through it before being written.
+static POST_FORMAT: OnceLock<String> = OnceLock::new();
+
+/// Set from `--entrypoint-mode`; read when the `dockerfile` and `init` commands build their spec.
+static ENTRYPOINT_MODE: Mutex<EntrypointMode> = Mutex::new(EntrypointMode::Exec);
+
+/// Runs the `--post-format` command on a temporary copy of `contents` named like `output_path`
+/// (formatters pick their parser from the extension) and returns the formatted text. A missing
+/// or failing formatter leaves the contents as rendered.
+fn post_format(output_path: &Path, contents: &str) -> io::Result<String> {
+    let Some(command) = POST_FORMAT.get() else {
+        return Ok(contents.to_string());
+    };
+    let mut words = command.split_whitespace();
+    let Some(program) = words.next() else {
+        return Ok(contents.to_string());
+    };
+    let file_name = output_path.file_name().map_or("output".into(), |n| n.to_string_lossy());
+    // A random suffix plus `create_new` keeps another user from planting a symlink at the path
+    let mut suffix = [0u8; 8];
+    getrandom::fill(&mut suffix).map_err(|e| io::Error::other(format!("cannot read random bytes: {}", e)))?;
+    let suffix: String = suffix.iter().map(|b| format!("{:02x}", b)).collect();
+    let temp = std::env::temp_dir().join(format!("configgen-{}-{}-{}", std::process::id(), suffix, file_name));
+    std::fs::OpenOptions::new().write(true).create_new(true).open(&temp)?.write_all(contents.as_bytes())?;
+    let result = std::process::Command::new(program).args(words).arg(&temp).output();
+    let formatted = std::fs::read_to_string(&temp);
+    let _ = std::fs::remove_file(&temp);
+
+    match result {
+        Ok(output) if output.status.success() => {
+            verbose!("formatted {} with {}", output_path.display(), command);
+            formatted
+        }
+        Ok(output) => {
+            warning!(
+                "--post-format failed on {}; leaving it unformatted: {}",
+                output_path.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            Ok(contents.to_string())
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            warning!("--post-format: '{}' is not installed; leaving {} unformatted", program, output_path.display());
+            Ok(contents.to_string())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+// =====================
+//       MANIFEST
+// =====================
+
+/// Every file written (or checked) this run, for `--manifest`.
+static WRITTEN_FILES: Mutex<Vec<(String, ManifestEntry)>> = Mutex::new(Vec::new());
+
+/// One file in `.configgen-manifest.json`. Fields are declared in sorted order so the
+/// JSON is stable.
+#[derive(Debug, Deserialize, Serialize)]
+struct ManifestEntry {
+    sha256: String,
+    /// Built-in template the file was rendered from; `None` for static files like wait-for-it.sh
+    /// and JSON compose files
+    template: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Manifest {
+    files: BTreeMap<String, ManifestEntry>,
+}
+
+fn record_written_file(output_path: &Path, contents: &str, template: Option<&str>) {
+    use sha2::Digest;
+    let template = template.map(String::from);
+    let sha256 = Sha256::digest(contents.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect();
+    let path = output_path.display().to_string();
+    WRITTEN_FILES.lock().unwrap().push((path, ManifestEntry { sha256, template }));
+}
+
+/// `path` relative to `dir` when it lies inside it, else unchanged. `.` components are ignored.
+fn manifest_path(path: &Path, dir: &Path) -> String {
+    let normal = |p: &Path| p.components().filter(|c| *c != std::path::Component::CurDir).collect::<PathBuf>();
+    let (path, dir) = (normal(path), normal(dir));
+    path.strip_prefix(&dir).unwrap_or(&path).display().to_string()
+}
+
+/// Merges this run's files into the manifest at `path`, so commands run one after another
+/// build up a single manifest.
+fn write_manifest(path: &Path, out: OutputOptions) -> io::Result<()> {
+    let mut manifest = match std::fs::read_to_string(path) {
+        Ok(existing) => serde_json::from_str::<Manifest>(&existing)
+            .map_err(|e| invalid_input(format!("{} is not a configgen manifest: {}", path.display(), e)))?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Manifest::default(),
+        Err(e) => return Err(e),
+    };
+    let written = std::mem::take(&mut *WRITTEN_FILES.lock().unwrap());
+    if written.is_empty() {
+        return Ok(());
+    }
+    // Entries are relative to the manifest, so the directory can be moved or checked out elsewhere
+    let dir = path.parent().unwrap_or(Path::new(""));
+    manifest.files.extend(written.into_iter().map(|(file, entry)| (manifest_path(Path::new(&file), dir), entry)));
+    let json = serde_json::to_string_pretty(&manifest).map_err(io::Error::other)? + "\n";
+    // Updating the manifest is expected, so it skips the overwrite policy
+    if out.check {
+        return check_file(path, &json);
+    }
+    if out.dry_run {
+        return preview_file(path, &json, out.diff);
+    }
+    // With --archive the output directory may not exist yet
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, json)?;
+    println!("Wrote manifest to: {}", path.display());
+    Ok(())
+}
+
+// =====================
+//       ARCHIVE
+// =====================
+
+/// A file collected for `--archive` instead of being written.
+struct ArchiveEntry {
+    /// Relative path inside the archive
+    path: String,
+    contents: Vec<u8>,
+    mode: u32,
+}
+
+/// Files collected this run for `--archive`, in the order they were generated.
+static ARCHIVE_ENTRIES: Mutex<Vec<ArchiveEntry>> = Mutex::new(Vec::new());
+
+/// Keeps only the normal components of `path` (`./out/Dockerfile` -> `out/Dockerfile`), so
+/// the archive never extracts outside the directory it is unpacked in.
+fn archive_path(path: &Path) -> String {
+    path.components()
+        .filter_map(|c| match c {
+            std::path::Component::Normal(part) => Some(part.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn add_archive_entry(output_path: &Path, contents: &str) {
+    let path = archive_path(output_path);
+    let mut entries = ARCHIVE_ENTRIES.lock().unwrap();
+    // A file generated twice (e.g. a split compose file) keeps its latest contents
+    entries.retain(|e| e.path != path);
+    entries.push(ArchiveEntry { path, contents: contents.as_bytes().to_vec(), mode: 0o644 });
+}
+
+fn mark_archive_entry_executable(output_path: &Path) {
+    let path = archive_path(output_path);
+    if let Some(entry) = ARCHIVE_ENTRIES.lock().unwrap().iter_mut().find(|e| e.path == path) {
+        entry.mode = 0o755;
+    }
+}
+
+/// Writes the files collected this run to a tar archive at `path`.
+fn write_archive(path: &Path) -> io::Result<()> {
+    let entries = std::mem::take(&mut *ARCHIVE_ENTRIES.lock().unwrap());
+    let mtime = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let mut builder = tar::Builder::new(File::create(path)?);
+    for entry in &entries {
+        let mut header = tar::Header::new_ustar();
+        header.set_size(entry.contents.len() as u64);
+        header.set_mode(entry.mode);
+        header.set_mtime(mtime);
+        builder.append_data(&mut header, &entry.path, entry.contents.as_slice())?;
+    }
+    builder.into_inner()?;
+    println!("Wrote {} file(s) to archive: {}", entries.len(), path.display());
+    Ok(())
+}
+
+/// Files found out of date by `--check`; any makes the run fail.
+static STALE_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Reports what `--dry-run` would write to `output_path`; with `diff`, shows how it differs from
+/// the file on disk as a colored unified diff.
+fn preview_file(output_path: &Path, contents: &str, diff: bool) -> std::io::Result<()> {
+    if !diff {
+        println!("Would write: {}", output_path.display());
+        return Ok(());
+    }
+    let existing = match std::fs::read_to_string(output_path) {
+        Ok(existing) => existing,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            println!("{}", paint(DIFF_HEADER, &format!("new file: {} ({} lines)", output_path.display(), contents.lines().count())));
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
+    if existing == contents {
+        verbose!("unchanged: {}", output_path.display());
+        return Ok(());
+    }
+    let path = output_path.display().to_string();
+    let diff = similar::TextDiff::from_lines(existing.as_str(), contents);
+    for line in diff.unified_diff().header(&path, &path).to_string().lines() {
+        let style = match line.as_bytes().first() {
+            _ if line.starts_with("---") || line.starts_with("+++") => DIFF_HEADER,
+            Some(b'@') => DIFF_HUNK,
+            Some(b'-') => ERROR,
+            Some(b'+') => DIFF_ADDED,
+            _ => Style::new(),
+        };
+        println!("{}", paint(style, line));
+    }
+    Ok(())
+}
+
+/// Compares rendered contents with the file on disk, printing a unified diff when they differ.
+fn check_file(output_path: &Path, contents: &str) -> std::io::Result<()> {
+    let existing = match std::fs::read_to_string(output_path) {
+        Ok(existing) => existing,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+    if existing == contents {
+        verbose!("up to date: {}", output_path.display());
+        return Ok(());
+    }
+
+    STALE_FILES.fetch_add(1, Ordering::Relaxed);
+    let path = output_path.display().to_string();
+    let diff = similar::TextDiff::from_lines(existing.as_str(), contents);
+    print!("{}", diff.unified_diff().header(&path, &path));
+    Ok(())
+}
+
+/// A generated file waiting for `flush_writes`.
+struct PendingWrite {
+    path: PathBuf,
+    contents: String,
+    template: Option<String>,
+    executable: bool,
+    out: OutputOptions,
+}
+
+/// Files generated this run. They are only written once the command has produced all of
+/// them, so a warning under `--strict` stops the run before anything reaches disk.
+static PENDING_WRITES: Mutex<Vec<PendingWrite>> = Mutex::new(Vec::new());
+
+/// Queues a generated file; `flush_writes` writes it (or checks, previews or archives it).
+fn write_to_file(output_path: &Path, contents: &str, template: Option<&str>, out: OutputOptions) -> std::io::Result<()> {
+    queue_write(output_path, contents, template, false, out)
+}
+
+fn queue_write(output_path: &Path, contents: &str, template: Option<&str>, executable: bool, out: OutputOptions) -> io::Result<()> {
+    // Formatting runs now so a failing formatter's warning counts before anything is written
+    let contents = post_format(output_path, contents)?;
+    PENDING_WRITES.lock().unwrap().push(PendingWrite {
+        path: output_path.to_path_buf(),
+        contents,
+        template: template.map(String::from),
+        executable,
+        out,
+    });
+    Ok(())
+}
+
+/// Writes every queued file, or none of them when `--strict` has turned warnings into errors.
+fn flush_writes() -> io::Result<()> {
+    let pending = std::mem::take(&mut *PENDING_WRITES.lock().unwrap());
+    let warnings = WARNINGS.load(Ordering::Relaxed);
+    if STRICT.load(Ordering::Relaxed) && warnings > 0 && !pending.is_empty() {
+        return Err(io::Error::other(format!(
+            "not writing {} file(s): {} warning(s) treated as errors (--strict)",
+            pending.len(),
+            warnings
+        )));
+    }
+    for write in &pending {
+        write_now(&write.path, &write.contents, write.template.as_deref(), write.out)?;
+        if write.executable {
+            mark_executable(&write.path, write.out)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_now(output_path: &Path, contents: &str, template: Option<&str>, out: OutputOptions) -> std::io::Result<()> {
+    if out.archive {
+        add_archive_entry(output_path, contents);
+        record_written_file(output_path, contents, template);
+        verbose!("added to archive: {}", output_path.display());
+        return Ok(());
+    }
+    if out.check && !(out.policy == OverwritePolicy::Skip && output_path.exists()) {
+        record_written_file(output_path, contents, template);
+        return check_file(output_path, contents);
+    }
+    if out.dry_run {
+        if out.policy == OverwritePolicy::Skip && output_path.exists() {
+            println!("Would skip existing file: {}", output_path.display());
+            return Ok(());
+        }
+        record_written_file(output_path, contents, template);
+        return preview_file(output_path, contents, out.diff);
+    }
+
+    if output_path.exists() {
+        match out.policy {
+            OverwritePolicy::Skip => {
+                println!("Skipped existing file: {}", output_path.display());
+                return Ok(());
+            }
+            OverwritePolicy::Overwrite => {
+                eprintln!("warning: overwriting existing file {}", output_path.display());
+            }
+            OverwritePolicy::Backup => {
+                let mut backup = output_path.as_os_str().to_owned();
+                backup.push(".bak");
+                std::fs::rename(output_path, &backup)?;
+                println!("Backed up existing file to: {}", Path::new(&backup).display());
+            }
+        }
+    }
+
+    let mut file = File::create(output_path)?;
+    file.write_all(contents.as_bytes())?;
+    record_written_file(output_path, contents, template);
+    println!("Wrote file to: {}", output_path.display());
+    Ok(())
+}
+
+/// Strips insignificant whitespace from rendered JSON while keeping key order.
+fn minify_json(json: &str) -> String {
+    let mut out = String::with_capacity(json.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in json.chars() {
+        if in_string {
+            out.push(c);
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => (),
+            }
+        } else if c == '"' {
+            in_string = true;
+            out.push(c);
+        } else if !c.is_whitespace() {
+            out.push(c);
+        }
+    }
+    out.push('\n');
+    out
+}
+
+/// Tightens rendered HCL: arrays collapse onto one line without a trailing comma,
+/// `key = value` loses its alignment padding and blank lines are dropped. Heredocs are kept as is.
+fn compact_hcl(hcl: &str) -> String {
+    let mut out = String::with_capacity(hcl.len());
+    let mut array: Option<(String, Vec<String>)> = None;
+    let mut heredoc: Option<&str> = None;
+    for line in hcl.lines() {
+        let trimmed = line.trim();
+        if let Some(delimiter) = heredoc {
+            if trimmed == delimiter {
+                heredoc = None;
+            }
+            out.push_str(line);
+            out.push('\n');
+            continue;
+        }
+        if let Some((_, delimiter)) = trimmed.rsplit_once("<<").filter(|(key, _)| key.contains('=')) {
+            heredoc = Some(delimiter.trim_start_matches('-'));
+        }
+        if let Some((prefix, items)) = &mut array {
+            if trimmed == "]" {
+                out.push_str(&format!("{}[{}]\n", prefix, items.join(", ")));
+                array = None;
+            } else {
+                items.push(trimmed.trim_end_matches(',').to_string());
+            }
+            continue;
+        }
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        let indent = &line[..line.len() - line.trim_start().len()];
+        let line = match trimmed.split_once('=') {
+            Some((key, value)) if !key.contains('"') => format!("{}{} = {}", indent, key.trim(), value.trim()),
+            _ => line.to_string(),
+        };
+        if let Some(prefix) = line.strip_suffix('[') {
+            array = Some((prefix.to_string(), Vec::new()));
+            continue;
+        }
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+fn default_devcontainer_settings() -> serde_json::Value {
+    json!({
+        "editor.formatOnSave": true,
+        "terminal.integrated.defaultProfile.linux": "bash"
+    })
+}
+
+/// Merges `--setting KEY=VALUE` entries over the default settings. Values that parse as
+/// JSON (`2`, `true`, `["a"]`) keep their type; anything else is stored as a string.
+fn devcontainer_settings(entries: &[String]) -> io::Result<serde_json::Value> {
+    let mut settings = default_devcontainer_settings();
+    for entry in entries {
+        let Some((key, value)) = entry.split_once('=') else {
+            return Err(invalid_input(format!("--setting expects KEY=VALUE, got '{}'", entry)));
+        };
+        let value = value.trim();
+        let value = serde_json::from_str(value).unwrap_or_else(|_| json!(value));
+        settings[key.trim()] = value;
+    }
+    Ok(settings)
+}
+
+/// Checks the `source=...,target=...,type=...` shape of a devcontainer mount.
+/// `source` may be left out for tmpfs mounts.
+fn validate_devcontainer_mount(mount: &str) -> io::Result<()> {
+    let invalid = |reason: &str| invalid_input(format!("invalid mount '{}': {}", mount, reason));
+
+    let mut fields = HashMap::new();
+    for part in mount.split(',') {
+        let Some((key, value)) = part.split_once('=') else {
+            return Err(invalid("expected comma-separated key=value pairs"));
+        };
+        fields.insert(key.trim(), value.trim());
+    }
+    let mount_type = fields.get("type").copied().unwrap_or_default();
+    if !["bind", "volume", "tmpfs"].contains(&mount_type) {
+        return Err(invalid("type must be bind, volume or tmpfs"));
+    }
+    if fields.get("target").is_none_or(|t| t.is_empty()) {
+        return Err(invalid("missing target"));
+    }
+    if mount_type != "tmpfs" && fields.get("source").is_none_or(|s| s.is_empty()) {
+        return Err(invalid("missing source"));
+    }
+    Ok(())
+}
+
+/// Queues a script that is marked executable once written.
+fn write_executable(output_path: &Path, contents: &str, template: Option<&str>, out: OutputOptions) -> std::io::Result<()> {
+    queue_write(output_path, contents, template, true, out)
+}
+
+fn mark_executable(output_path: &Path, out: OutputOptions) -> io::Result<()> {
+    if out.archive {
+        mark_archive_entry_executable(output_path);
+        return Ok(());
+    }
+    if out.check || out.dry_run {
+        return Ok(());
+    }
+    make_executable(output_path)
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Runs `git -C dir <args>`. Returns `None` when git isn't installed.
+fn git<I, S>(dir: &str, args: I) -> io::Result<Option<std::process::Output>>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<std::ffi::OsStr>,
+{
+    match std::process::Command::new("git").arg("-C").arg(dir).args(args).output() {
+        Ok(output) => Ok(Some(output)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// OCI `revision` and `source` labels for the git checkout in the current directory.
+/// Outside a repository this warns and returns no labels.
+fn git_source_labels() -> io::Result<Vec<(String, String)>> {
+    let stdout = |o: std::process::Output| o.status.success().then(|| String::from_utf8_lossy(&o.stdout).trim().to_string());
+    let Some(revision) = git(".", ["rev-parse", "HEAD"])?.and_then(stdout) else {
+        warning!("--labels-from-git: not in a git repository with commits (or git is not installed); no labels added");
+        return Ok(Vec::new());
+    };
+    let mut labels = vec![("org.opencontainers.image.revision".to_string(), revision)];
+    match git(".", ["remote", "get-url", "origin"])?.and_then(stdout) {
+        Some(remote) => labels.push(("org.opencontainers.image.source".to_string(), source_url(&remote))),
+        None => warning!("--labels-from-git: no 'origin' remote, so no org.opencontainers.image.source label"),
+    }
+    Ok(labels)
+}
+
+/// Name, version, description and license of the project in the current directory, as declared
+/// in its Cargo.toml `[package]` table or package.json.
+#[derive(Debug, Default)]
+struct ProjectMetadata {
+    name: Option<String>,
+    version: Option<String>,
+    description: Option<String>,
+    license: Option<String>,
+}
+
+fn detect_project_metadata() -> ProjectMetadata {
+    if let Ok(manifest) = std::fs::read_to_string("Cargo.toml") {
+        let mut metadata = ProjectMetadata::default();
+        let mut in_package = false;
+        for line in manifest.lines().map(str::trim) {
+            if line.starts_with('[') {
+                in_package = line == "[package]";
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=').filter(|_| in_package) else { continue };
+            // Only plain strings; `version.workspace = true` and the like are skipped
+            let Some(value) = value.trim().strip_prefix('"').and_then(|v| v.strip_suffix('"')) else { continue };
+            let field = match key.trim() {
+                "name" => &mut metadata.name,
+                "version" => &mut metadata.version,
+                "description" => &mut metadata.description,
+                "license" => &mut metadata.license,
+                _ => continue,
+            };
+            // Undo TOML's basic-string escapes for quotes and backslashes
+            *field = Some(value.replace("\\\\", "\0").replace("\\\"", "\"").replace('\0', "\\"));
+        }
+        return metadata;
+    }
+    if let Ok(manifest) = std::fs::read_to_string("package.json") {
+        let package: serde_json::Value = serde_json::from_str(&manifest).unwrap_or_default();
+        let field = |key: &str| package.get(key).and_then(|v| v.as_str()).map(String::from);
+        return ProjectMetadata {
+            name: field("name"),
+            version: field("version"),
+            description: field("description"),
+            license: field("license"),
+        };
+    }
+    ProjectMetadata::default()
+}
+
+/// `org.opencontainers.image.*` labels for the fields that have a value, quoted for a Dockerfile.
+fn oci_metadata_labels<const N: usize>(fields: [(&str, Option<String>); N]) -> Vec<(String, String)> {
+    fields
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let value = value?.replace('\\', "\\\\").replace('"', "\\\"");
+            Some((format!("org.opencontainers.image.{}", key), value))
+        })
+        .collect()
+}
+
+/// The current UTC time as RFC 3339 (`2024-01-01T12:00:00Z`). `SOURCE_DATE_EPOCH` overrides the
+/// clock so reproducible builds (and `--check`) see a stable value.
+fn rfc3339_now() -> String {
+    let secs = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.trim().parse::<i64>().ok())
+        .unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs() as i64)
+        });
+    let (days, time) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, time / 3600, time % 3600 / 60, time % 60
+    )
+}
+
+/// Checks the `YYYY-MM-DDTHH:MM:SS` shape with optional fractional seconds and a `Z` or `±HH:MM` offset.
+fn is_rfc3339(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    let digits = |range: std::ops::Range<usize>| bytes.get(range).is_some_and(|b| b.iter().all(u8::is_ascii_digit));
+    let shape = digits(0..4)
+        && bytes.get(4) == Some(&b'-')
+        && digits(5..7)
+        && bytes.get(7) == Some(&b'-')
+        && digits(8..10)
+        && matches!(bytes.get(10), Some(b'T' | b't'))
+        && digits(11..13)
+        && bytes.get(13) == Some(&b':')
+        && digits(14..16)
+        && bytes.get(16) == Some(&b':')
+        && digits(17..19);
+    if !shape {
+        return false;
+    }
+    let mut rest = &value[19..];
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let len = fraction.chars().take_while(char::is_ascii_digit).count();
+        if len == 0 {
+            return false;
+        }
+        rest = &fraction[len..];
+    }
+    match rest.as_bytes() {
+        [b'Z' | b'z'] => true,
+        [b'+' | b'-', h1, h2, b':', m1, m2] => [h1, h2, m1, m2].iter().all(|b| b.is_ascii_digit()),
+        _ => false,
+    }
+}
+
+/// Turns a git remote into a browsable URL without credentials
+/// (`git@github.com:me/app.git` -> `https://github.com/me/app`).
+fn source_url(remote: &str) -> String {
+    let remote = remote.trim().trim_end_matches('/').trim_end_matches(".git");
+    let (scheme, rest) = match remote.split_once("://") {
+        Some((scheme, rest)) => (scheme, rest.to_string()),
+        // scp-style `user@host:path`
+        None => match remote.split_once(':') {
+            Some((host, path)) => ("ssh", format!("{}/{}", host, path)),
+            None => return remote.to_string(),
+        },
+    };
+    let (authority, path) = rest.split_once('/').unwrap_or((rest.as_str(), ""));
+    let host = authority.rsplit('@').next().unwrap_or(authority);
+    match scheme {
+        "http" | "https" => format!("{}://{}/{}", scheme, host, path),
+        // An ssh port says nothing about where the web UI lives
+        _ => format!("https://{}/{}", host.split(':').next().unwrap_or(host), path),
+    }
+}
+
+/// Initializes a repository in `dir` and commits `files` (paths directly inside it).
+/// A failing git step is reported as a warning; the generated files are already written.
+fn init_git_repo(dir: &str, files: &[PathBuf]) -> io::Result<()> {
+    let names = files.iter().filter_map(|f| Some(f.strip_prefix(dir).ok()?.as_os_str()));
+    let steps: [(&str, Vec<&std::ffi::OsStr>); 3] = [
+        ("init", vec!["init".as_ref()]),
+        ("add", ["add".as_ref(), "--".as_ref()].into_iter().chain(names).collect()),
+        ("commit", vec!["commit".as_ref(), "-m".as_ref(), "Initial commit".as_ref()]),
+    ];
+    for (step, args) in steps {
+        let Some(output) = git(dir, args)? else {
+            eprintln!("warning: git is not installed; skipping --git-init");
+            return Ok(());
+        };
+        if !output.status.success() {
+            eprintln!("warning: git {} failed: {}", step, String::from_utf8_lossy(&output.stderr).trim());
+            return Ok(());
+        }
+    }
+    println!("Initialized a git repository with an initial commit in: {}", dir);
+    Ok(())
+}
+
+/// Returns the container side of a compose port mapping (`"8080:80/tcp"` -> `"80"`).
+fn container_port(mapping: &str) -> &str {
+    let mapping = mapping.split('/').next().unwrap_or(mapping);
+    mapping.rsplit(':').next().unwrap_or(mapping)
+}
+
+// =====================
+//     NOMAD EXPORT
+// =====================
+
+/// Converts a compose spec into a single-group Nomad job with a docker task per service. Only
+/// images, ports, entrypoint/command and environment carry over; volumes, builds and the
+/// rest of the compose-only settings are reported and left out.
+fn nomad_job(spec: &DockerComposeSpec, job: String, datacenter: String) -> io::Result<NomadJobSpec> {
+    // HCL strings use JSON-style escapes
+    let quote = |value: &str| value.replace('\\', "\\\\").replace('"', "\\\"");
+    let mut ports = Vec::new();
+    let mut tasks = Vec::new();
+    for service in &spec.services {
+        let image = if service.image.is_empty() {
+            warning!("nomad: '{}' is built from source; push the image and set its name (using {}:latest)", service.name, service.name);
+            format!("{}:latest", service.name)
+        } else {
+            service.image.clone()
+        };
+        if !service.volumes.is_empty() {
+            warning!("nomad: volumes of '{}' are not converted", service.name);
+        }
+        let mut task_ports = Vec::new();
+        for mapping in &service.ports {
+            let parts: Vec<&str> = mapping.split('/').next().unwrap_or(mapping).split(':').collect();
+            let (host, container) = match parts.as_slice() {
+                [container] => (None, *container),
+                [host, container] | [_, host, container] => (Some(*host).filter(|h| !h.is_empty()), *container),
+                _ => (None, ""),
+            };
+            let Ok(to) = container.parse::<u16>() else {
+                warning!("nomad: port '{}' of '{}' is not a single port mapping and was left out", mapping, service.name);
+                continue;
+            };
+            let static_port = match host.map(str::parse::<u16>) {
+                Some(Ok(port)) => Some(port),
+                Some(Err(_)) => {
+                    warning!("nomad: port '{}' of '{}' is not a single port mapping and was left out", mapping, service.name);
+                    continue;
+                }
+                None => None,
+            };
+            // Port labels share one namespace across the group
+            let base = service.name.replace(|c: char| !c.is_ascii_alphanumeric(), "_");
+            let label = if task_ports.is_empty() { base } else { format!("{}_{}", base, task_ports.len() + 1) };
+            task_ports.push(label.clone());
+            ports.push(NomadPort { label, static_port, to });
+        }
+        let mut command = service.command.iter().map(|arg| quote(arg));
+        let env = if service.shared_env { spec.common_env.iter().chain(&service.environment).collect::<Vec<_>>() } else { service.environment.iter().collect() };
+        tasks.push(NomadTask {
+            name: service.name.clone(),
+            image,
+            ports: task_ports,
+            entrypoint: service.entrypoint.iter().map(|arg| quote(arg)).collect(),
+            command: command.next(),
+            args: command.collect(),
+            env: env.into_iter().map(|(key, value)| (key.clone(), quote(value))).collect(),
+        });
+    }
+    if tasks.is_empty() {
+        return Err(invalid_input("nomad: the compose section has no services".to_string()));
+    }
+    Ok(NomadJobSpec { job: quote(&job), datacenter: quote(&datacenter), ports, tasks })
+}
+
+// =====================
+//      VALIDATION
+// =====================
+
+/// Returns the host side of a compose port mapping (`"8080:80"` -> `"8080"`),
+/// or `None` when only a container port is given.
+fn host_port(mapping: &str) -> Option<&str> {
+    let mapping = mapping.split('/').next().unwrap_or(mapping);
+    let (host, _container) = mapping.rsplit_once(':')?;
+    // Drop any bind address so "127.0.0.1:8080" and "8080" collide
+    Some(host.rsplit(':').next().unwrap_or(host))
+}
+
+/// Checks a compose spec for problems that would stop the stack from starting.
+/// `merged_with` names the services of the files this one is merged with, which it may
+/// refer to. Returns one human-readable message per finding.
+fn validate_compose_spec(spec: &DockerComposeSpec, merged_with: &[String]) -> Vec<String> {
+    let mut findings = Vec::new();
+
+    let mut names = HashSet::new();
+    for service in &spec.services {
+        if !names.insert(service.name.as_str()) {
+            findings.push(format!("duplicate service name '{}'", service.name));
+        }
+    }
+    let defined: HashSet<&str> = names.iter().copied().chain(merged_with.iter().map(String::as_str)).collect();
+
+    for service in &spec.services {
+        for dep in &service.depends_on {
+            if !defined.contains(dep.service.as_str()) {
+                findings.push(format!(
+                    "service '{}' depends on undefined service '{}'",
+                    service.name, dep.service
+                ));
+            }
+        }
+    }
+
+    if let Err(cycle) = dependency_order(&spec.services) {
+        findings.push(format!("depends_on cycle between {}", quoted_list(&cycle)));
+    }
+
+    for service in &spec.services {
+        if let Some(problem) = network_mode_problem(service, &defined) {
+            findings.push(problem);
+        }
+    }
+
+    let mut host_ports: HashMap<&str, &str> = HashMap::new();
+    for service in &spec.services {
+        for port in &service.ports {
+            let Some(host) = host_port(port) else { continue };
+            if let Some(other) = host_ports.insert(host, &service.name) {
+                findings.push(format!(
+                    "host port {} is published by both '{}' and '{}'",
+                    host, other, service.name
+                ));
+            }
+        }
+    }
+
+    findings
+}
+
+/// Orders services so each comes after everything it `depends_on`, keeping the original order
+/// where dependencies allow. Dependencies on undefined services are ignored. Fails with the
+/// names of the services that could not be placed when `depends_on` contains a cycle.
+fn dependency_order(services: &[ServiceSpec]) -> Result<Vec<usize>, Vec<String>> {
+    let defined: HashSet<&str> = services.iter().map(|s| s.name.as_str()).collect();
+    let mut placed: HashSet<&str> = HashSet::new();
+    let mut order = Vec::with_capacity(services.len());
+    let mut pending: Vec<usize> = (0..services.len()).collect();
+
+    while !pending.is_empty() {
+        let ready = pending.iter().position(|&i| {
+            services[i]
+                .depends_on
+                .iter()
+                .all(|dep| placed.contains(dep.service.as_str()) || !defined.contains(dep.service.as_str()))
+        });
+        let Some(position) = ready else {
+            return Err(pending.iter().map(|&i| services[i].name.clone()).collect());
+        };
+        let index = pending.remove(position);
+        placed.insert(&services[index].name);
+        order.push(index);
+    }
+
+    Ok(order)
+}
+
+/// Formats names as `'a', 'b'`.
+fn quoted_list(names: &[String]) -> String {
+    names.iter().map(|n| format!("'{}'", n)).collect::<Vec<_>>().join(", ")
+}
+
+/// Checks a service's `network_mode` against the other services and the keys it conflicts with.
+fn network_mode_problem(service: &ServiceSpec, services: &HashSet<&str>) -> Option<String> {
+    let mode = service.network_mode.as_deref()?;
+    match mode.split_once(':') {
+        Some(("service", target)) if target == service.name => {
+            Some(format!("service '{}' uses its own network stack (network_mode: {})", service.name, mode))
+        }
+        Some(("service", target)) if !services.contains(target) => Some(format!(
+            "service '{}' uses the network of undefined service '{}'",
+            service.name, target
+        )),
+        Some(("service" | "container", target)) if !target.is_empty() => None,
+        None if matches!(mode, "host" | "none" | "bridge") => None,
+        _ => Some(format!(
+            "service '{}' has invalid network_mode '{}' (expected host, none, bridge, service:NAME or container:NAME)",
+            service.name, mode
+        )),
+    }
+    .or_else(|| {
+        (service.hostname.is_some() && !matches!(mode, "bridge" | "none")).then(|| {
+            format!("service '{}' sets hostname, which conflicts with network_mode: {}", service.name, mode)
+        })
+    })
+}
+
+/// Service entries in file order, keeping duplicates that a plain map would reject.
+struct ServiceEntries(Vec<(String, serde_yaml::Value)>);
+
+impl<'de> Deserialize<'de> for ServiceEntries {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EntriesVisitor;
+
+        impl<'de> Visitor<'de> for EntriesVisitor {
+            type Value = ServiceEntries;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a mapping of service names to service definitions")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(ServiceEntries(entries))
+            }
+        }
+
+        deserializer.deserialize_map(EntriesVisitor)
+    }
+}
+
+#[derive(Deserialize)]
+struct ComposeFile {
+    services: Option<ServiceEntries>,
+}
+
+/// Reads a YAML list of scalars (strings or numbers) as strings.
+fn yaml_string_list(value: &serde_yaml::Value) -> Vec<String> {
+    value
+        .as_sequence()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| match item {
+                    serde_yaml::Value::String(s) => Some(s.clone()),
+                    serde_yaml::Value::Number(n) => Some(n.to_string()),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Converts a loosely-typed compose service into a `ServiceSpec`, recording
+/// empty blocks (keys with no value) as findings along the way.
+fn service_from_yaml(name: &str, value: &serde_yaml::Value, findings: &mut Vec<String>) -> ServiceSpec {
+    let mut service = ServiceSpec {
+        name: name.to_string(),
+        ..Default::default()
+    };
+
+    let Some(fields) = value.as_mapping() else {
+        findings.push(format!("service '{}' is empty", name));
+        return service;
+    };
+
+    for (key, field) in fields {
+        let Some(key) = key.as_str() else { continue };
+        if field.is_null() {
+            findings.push(format!("service '{}' has an empty '{}' block", name, key));
+            continue;
+        }
+        match key {
+            "image" => service.image = field.as_str().unwrap_or_default().to_string(),
+            "hostname" => service.hostname = field.as_str().map(String::from),
+            "network_mode" => service.network_mode = field.as_str().map(String::from),
+            "ports" => service.ports = yaml_string_list(field),
+            "volumes" => service.volumes = yaml_string_list(field),
+            "depends_on" => {
+                service.depends_on = match field.as_mapping() {
+                    Some(deps) => deps
+                        .iter()
+                        .filter_map(|(k, v)| {
+                            Some(Dependency {
+                                service: k.as_str()?.to_string(),
+                                condition: v.get("condition").and_then(|c| c.as_str()).map(String::from),
+                                restart: v.get("restart").and_then(|r| r.as_bool()).unwrap_or(false),
+                            })
+                        })
+                        .collect(),
+                    None => yaml_string_list(field).iter().map(|dep| Dependency::new(dep)).collect(),
+                }
+            }
+            "environment" => {
+                service.environment = match field.as_mapping() {
+                    Some(vars) => vars
+                        .iter()
+                        .filter_map(|(k, v)| {
+                            let v = match v {
+                                serde_yaml::Value::String(s) => s.clone(),
+                                other => serde_yaml::to_string(other).ok()?.trim().to_string(),
+                            };
+                            Some((k.as_str()?.to_string(), v))
+                        })
+                        .collect(),
+                    None => yaml_string_list(field)
+                        .iter()
+                        .filter_map(|pair| {
+                            pair.split_once('=').map(|(k, v)| (k.to_string(), v.to_string()))
+                        })
+                        .collect(),
+                }
+            }
+            _ => (),
+        }
+    }
+
+    if service.network_mode.is_some() && fields.contains_key("networks") {
+        findings.push(format!("service '{}' sets both 'network_mode' and 'networks'", name));
+    }
+
+    service
+}
+
+/// Lints the contents of a docker-compose file.
+fn validate_compose_file(contents: &str) -> Vec<String> {
+    let file: ComposeFile = match serde_yaml::from_str(contents) {
+        Ok(file) => file,
+        Err(e) => return vec![format!("invalid compose file: {}", e)],
+    };
+
+    let mut findings = Vec::new();
+    let entries = match file.services {
+        Some(entries) if !entries.0.is_empty() => entries.0,
+        _ => return vec!["compose file defines no services".to_string()],
+    };
+
+    let services = entries
+        .iter()
+        .map(|(name, value)| service_from_yaml(name, value, &mut findings))
+        .collect();
+    findings.extend(validate_compose_spec(
+        &DockerComposeSpec {
+            services,
+            ..Default::default()
+        },
+        &[],
+    ));
+    findings
+}
+
+const DOCKERFILE_INSTRUCTIONS: &[&str] = &[
+    "ADD", "ARG", "CMD", "COPY", "ENTRYPOINT", "ENV", "EXPOSE", "FROM", "HEALTHCHECK",
+    "LABEL", "MAINTAINER", "ONBUILD", "RUN", "SHELL", "STOPSIGNAL", "USER", "VOLUME", "WORKDIR",
+];
+
+/// Checks a Dockerfile spec for common image-quality issues (`--lint`).
+fn lint_dockerfile(spec: &DockerfileSpec) -> Vec<String> {
+    let mut findings = Vec::new();
+    let bases = std::iter::once(&spec.base_image).chain(spec.runtime.as_ref().map(|r| &r.base_image));
+    for image in bases {
+        // A registry port (`host:5000/img`) is not a tag, so only look after the last '/'
+        let name = image.rsplit('/').next().unwrap_or(image);
+        let unpinned = !image.contains('@') && name.split_once(':').is_none_or(|(_, tag)| tag == "latest");
+        if unpinned {
+            findings.push(format!("base image '{}' floats on :latest; pin a version tag or digest", image));
+        }
+    }
+    if spec.user.as_deref().is_none_or(|user| user == "root" || user == "0" || user.starts_with("root:") || user.starts_with("0:")) {
+        findings.push("the image runs as root; set a non-root USER".to_string());
+    }
+    if !spec.packages.is_empty() && spec.install_recommends {
+        findings.push("apt-get install pulls in recommended packages; add --no-install-recommends".to_string());
+    }
+    if spec.workdir.is_empty() || spec.workdir == "/" {
+        findings.push("no WORKDIR is set, so files land in the filesystem root".to_string());
+    }
+    findings
+}
+
+/// Structural checks `--compose-lint` adds to `validate_compose_spec`: references the rendered
+/// file can't resolve and required fields left empty. Services also defined in `merged_with`
+/// files may leave out the image and build.
+fn lint_compose_spec(spec: &DockerComposeSpec, merged_with: &[String]) -> Vec<String> {
+    let mut findings = Vec::new();
+    for service in &spec.services {
+        if service.name.trim().is_empty() {
+            findings.push("a service has an empty name".to_string());
+            continue;
+        }
+        if service.image.is_empty() && service.build.is_none() && !merged_with.contains(&service.name) {
+            findings.push(format!("service '{}' has neither an image nor a build", service.name));
+        }
+        for (what, entries) in [("port", &service.ports), ("volume", &service.volumes)] {
+            if entries.iter().any(|entry| entry.trim().is_empty()) {
+                findings.push(format!("service '{}' has an empty {} entry", service.name, what));
+            }
+        }
+        if service.environment.iter().any(|(key, _)| key.trim().is_empty()) {
+            findings.push(format!("service '{}' has an environment entry without a name", service.name));
+        }
+        // The file declares no top-level volumes, so compose rejects every named volume
+        for volume in &service.volumes {
+            if let Some((source, _)) = volume.split_once(':').filter(|(source, _)| !source.is_empty() && !is_bind_mount(source)) {
+                findings.push(format!("service '{}' mounts undeclared named volume '{}'", service.name, source));
+            }
+        }
+        for dep in &service.depends_on {
+            if dep.service.trim().is_empty() {
+                findings.push(format!("service '{}' has a depends_on entry without a service", service.name));
+            }
+        }
+    }
+    if spec.networks.keys().any(|name| name.trim().is_empty()) {
+        findings.push("a network has an empty name".to_string());
+    }
+    findings
+}
+
+/// Checks the compose spec written to `path`, printing every finding as a warning. Writes are
+/// queued until the command finishes, so under `--strict` nothing is written. `merged_with` names
+/// the services of the files this one is merged with (the base file of an override, the other
+/// `--split-by` files). `--compose-lint` adds `lint_compose_spec`.
+fn report_compose_lint(path: &Path, spec: &DockerComposeSpec, merged_with: &[String], out: OutputOptions) {
+    let mut findings = validate_compose_spec(spec, merged_with);
+    if out.compose_lint {
+        findings.extend(lint_compose_spec(spec, merged_with));
+    }
+    for finding in findings {
+        warning!("{}: {}", path.display(), finding);
+    }
+}
+
+/// Prints `--lint` findings for the Dockerfile written to `path` as warnings.
+fn report_lint(path: &Path, spec: &DockerfileSpec, out: OutputOptions) {
+    if out.lint {
+        for finding in lint_dockerfile(spec) {
+            warning!("{}: {}", path.display(), finding);
+        }
+    }
+}
+
+/// Lints the contents of a Dockerfile.
+fn validate_dockerfile(contents: &str) -> Vec<String> {
+    let mut findings = Vec::new();
+    let mut seen_from = false;
+    let mut continued = false;
+
+    for (i, line) in contents.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let word = trimmed.split_whitespace().next().unwrap_or_default();
+        let is_instruction = DOCKERFILE_INSTRUCTIONS.contains(&word.to_uppercase().as_str());
+
+        if continued && is_instruction && word == word.to_uppercase() {
+            findings.push(format!(
+                "line {}: {} is swallowed by the line continuation above it",
+                i + 1,
+                word
+            ));
+        } else if !continued {
+            if !is_instruction {
+                findings.push(format!("line {}: unknown instruction '{}'", i + 1, word));
+            } else if word.eq_ignore_ascii_case("FROM") {
+                seen_from = true;
+            } else if !seen_from && !word.eq_ignore_ascii_case("ARG") {
+                findings.push(format!("line {}: {} appears before FROM", i + 1, word));
+            }
+            if word.eq_ignore_ascii_case("RUN") && trimmed.len() == word.len() {
+                findings.push(format!("line {}: empty RUN instruction", i + 1));
+            }
+        }
+
+        continued = trimmed.ends_with('\\');
+    }
+
+    if !seen_from {
+        findings.push("Dockerfile has no FROM instruction".to_string());
+    }
+    if continued {
+        findings.push("Dockerfile ends with a dangling line continuation".to_string());
+    }
+    findings
+}
+
+// =====================
+//     CLI COMMANDS
+// =====================
+
+#[derive(Parser)]
+#[command(
+    name = "configgen",
+    version = "0.1.0",
+    about = "Generates Docker/OCI-related config files in pure Rust!"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+    /// Print the resolved spec and template for each generated file to stderr
+    #[arg(short, long, global = true)]
+    verbose: bool,
+    /// What to do with output files that already exist
+    #[arg(long, value_enum, global = true, default_value_t = OverwritePolicy::Overwrite)]
+    overwrite_policy: OverwritePolicy,
+    /// Don't write anything; fail with a diff if the files on disk differ from what would be generated
+    #[arg(long, global = true)]
+    check: bool,
+    /// Treat warnings (unknown capabilities, port collisions, ignored list entries, ...) as errors
+    #[arg(long, global = true)]
+    strict: bool,
+    /// Never fall back to interactive prompts; also enabled by CONFIGGEN_NONINTERACTIVE
+    #[arg(long, global = true)]
+    no_interactive: bool,
+    /// Formatter run on each generated file before it is written, given the file path as its last
+    /// argument (e.g., "prettier --write")
+    #[arg(long, global = true)]
+    post_format: Option<String>,
+    /// Record every written file with its template and SHA-256 in a manifest (default: .configgen-manifest.json)
+    #[arg(long, global = true, num_args = 0..=1, require_equals = true, value_name = "FILE", default_missing_value = ".configgen-manifest.json")]
+    manifest: Option<String>,
+    /// Warn about :latest base images, running as root, recommended apt packages and a missing WORKDIR
+    /// in generated Dockerfiles (errors under --strict)
+    #[arg(long, global = true)]
+    lint: bool,
+    /// Also check every compose file for undeclared volumes, empty names/entries and services with
+    /// neither image nor build before writing it (errors under --strict)
+    #[arg(long, global = true)]
+    compose_lint: bool,
+    /// Render every file but write nothing, listing the files that would be written
+    #[arg(long, global = true, conflicts_with_all = ["check", "archive"])]
+    dry_run: bool,
+    /// With --dry-run, print a unified diff of each file against what's on disk
+    #[arg(long, global = true, requires = "dry_run")]
+    diff: bool,
+    /// Bundle every generated file into this tar archive, keeping relative paths, instead of writing them
+    #[arg(long, global = true, value_name = "FILE.tar", conflicts_with = "check")]
+    archive: Option<String>,
+    /// Write generated Dockerfile entrypoints in exec form (["cmd"]) or shell form (cmd)
+    #[arg(long, value_enum, global = true, default_value_t = EntrypointMode::Exec)]
+    entrypoint_mode: EntrypointMode,
+}
+
+// Parsed once per run, so variant size doesn't matter
+#[allow(clippy::large_enum_variant)]
+#[derive(Subcommand)]
+enum Commands {
+    /// Generate only a Dockerfile
+    Dockerfile {
+        /// Base image to use
+        #[arg(long, default_value = "ubuntu:22.04")]
+        base_image: String,
+        /// Name/email of maintainer
+        #[arg(long, default_value = "Jane Doe <jane@example.com>")]
+        maintainer: String,
+        /// Comma-separated list of packages (default: curl,git unless --packages-file is given)
+        #[arg(long)]
+        packages: Option<String>,
+        /// File listing one package per line (blank lines and # comments are ignored)
+        #[arg(long)]
+        packages_file: Option<String>,
+        /// Pin the Debian package index to snapshot.debian.org at this date (e.g., "2024-01-01" or "20240101T000000Z")
+        #[arg(long)]
+        snapshot_date: Option<String>,
+        /// Run apt-get update and install in one layer or in separate layers
+        #[arg(long, value_enum, default_value_t = AptLayers::Combined)]
+        apt_layers: AptLayers,
+        /// Let apt-get install recommended packages too (by default it runs with --no-install-recommends)
+        #[arg(long, overrides_with = "no_recommends")]
+        recommends: bool,
+        /// Keep recommended packages out with --no-install-recommends (the default)
+        #[arg(long, overrides_with = "recommends")]
+        no_recommends: bool,
+        /// Add OCI revision/source labels from the current git checkout
+        #[arg(long)]
+        labels_from_git: bool,
+        /// Add the standard OCI title/description/version/created/licenses labels, taking values
+        /// from the --oci-* flags or the Cargo.toml/package.json in the current directory
+        #[arg(long)]
+        oci_metadata: bool,
+        /// org.opencontainers.image.title (default: the project name, else the directory name)
+        #[arg(long, requires = "oci_metadata")]
+        oci_title: Option<String>,
+        /// org.opencontainers.image.description
+        #[arg(long, requires = "oci_metadata")]
+        oci_description: Option<String>,
+        /// org.opencontainers.image.version
+        #[arg(long, requires = "oci_metadata")]
+        oci_version: Option<String>,
+        /// org.opencontainers.image.licenses as an SPDX expression (e.g., "MIT OR Apache-2.0")
+        #[arg(long, requires = "oci_metadata")]
+        oci_licenses: Option<String>,
+        /// org.opencontainers.image.created as RFC 3339 (default: now, or $SOURCE_DATE_EPOCH)
+        #[arg(long, requires = "oci_metadata")]
+        oci_created: Option<String>,
+        /// Pin the base image to the digest recorded for it in this lockfile ("IMAGE DIGEST" per line)
+        #[arg(long, value_name = "LOCKFILE")]
+        base_image_from_lock: Option<String>,
+        /// Pin the base image to this digest (e.g., "sha256:...") without consulting a lockfile
+        #[arg(long, conflicts_with = "base_image_from_lock")]
+        digest: Option<String>,
+        /// Platform the image is built for, written as FROM --platform= (e.g., "linux/arm64")
+        #[arg(long, conflicts_with_all = ["amd64", "arm64"])]
+        platform: Option<String>,
+        /// Build for linux/amd64; with --arm64 too, the --bake target builds both
+        #[arg(long)]
+        amd64: bool,
+        /// Build for linux/arm64; with --amd64 too, the --bake target builds both
+        #[arg(long)]
+        arm64: bool,
+        /// Build context for the --bake/--compose build config (default: ".")
+        #[arg(long)]
+        context: Option<String>,
+        /// Also write a bake file with an "app" target building this Dockerfile
+        #[arg(long, num_args = 0..=1, value_name = "FILE", default_missing_value = "docker-bake.hcl")]
+        bake: Option<String>,
+        /// Also write a compose file with an "app" service building this Dockerfile
+        #[arg(long, num_args = 0..=1, value_name = "FILE", default_missing_value = "compose.yaml")]
+        compose: Option<String>,
+        /// Working directory in container
+        #[arg(long, default_value = "/app")]
+        workdir: String,
+        /// User the image runs as (e.g., "app" or "1000:1000")
+        #[arg(long)]
+        user: Option<String>,
+        /// Entrypoint
+        #[arg(long, default_value = "/bin/bash")]
+        entrypoint: String,
+        /// Output filename
+        #[arg(short, long, default_value = "Dockerfile")]
+        output: String,
+    },
+    /// Generate a docker-compose.yml with customizable services
+    Compose {
+        /// Output filename (overrides --filename-convention)
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Default compose filename to use when --output is not given
+        #[arg(long, value_enum, default_value_t = FilenameConvention::Modern)]
+        filename_convention: FilenameConvention,
+        /// Write the compose files as YAML or JSON
+        #[arg(long, value_enum, default_value_t = ComposeFormat::Yaml)]
+        format: ComposeFormat,
+        /// Comma-separated list of services to include (e.g., nginx,postgres,redis)
+        #[arg(long)]
+        services: Option<String>,
+        /// Add --count near-identical services from NAME{n}:IMAGE, with {n} counting from 1
+        /// (e.g., "worker{n}:myimage:latest")
+        #[arg(long, value_name = "NAME{n}:IMAGE", requires = "count")]
+        service_template: Option<String>,
+        /// Number of services --service-template expands into
+        #[arg(long, requires = "service_template")]
+        count: Option<u16>,
+        /// Port of the first templated service; each further one gets the next host port (e.g., "9000:8080")
+        #[arg(long, requires = "service_template")]
+        template_port: Option<String>,
+        /// Ports per service, by position ("80:80,5432:5432") or by name ("web=80:80;443:443,db=5432:5432")
+        #[arg(long)]
+        ports: Option<String>,
+        /// Volumes per service, by position or by name (e.g., "db=./data:/var/lib/postgresql/data")
+        #[arg(long)]
+        volumes: Option<String>,
+        /// Comma-separated list of environment variables (e.g., "POSTGRES_USER=admin,POSTGRES_PASSWORD=secret")
+        #[arg(long)]
+        env: Option<String>,
+        /// Expand `${VAR}` (or `${VAR:-default}`) in --env values from the host environment now,
+        /// instead of leaving them for Docker Compose to interpolate at runtime
+        #[arg(long)]
+        expand_env: bool,
+        /// Comma-separated list of networks to create (defaults to bridge driver)
+        #[arg(long, default_value = "app_network")]
+        networks: String,
+        /// Comma-separated list of service dependencies (e.g., "web:db,cache:db").
+        /// A service can list several, either repeated ("web:db,web:redis") or as "web:db|redis"
+        #[arg(long)]
+        depends_on: Option<String>,
+        /// Also write a dev override file holding the --dev-only settings
+        /// (defaults to compose.override.yaml, or docker-compose.override.yml for legacy)
+        #[arg(long, num_args = 0..=1, value_name = "FILE")]
+        override_output: Option<Option<String>>,
+        /// Settings that belong in the override file only
+        #[arg(long, value_enum, value_delimiter = ',', default_value = "ports,mounts")]
+        dev_only: Vec<DevOnlySetting>,
+        /// Tailor the services for development (source mounts) or production (built images,
+        /// restart: always, no --dev-services)
+        #[arg(long, value_enum)]
+        environment: Option<Environment>,
+        /// Comma-separated services only wanted in development (e.g., "adminer,mailpit");
+        /// --environment prod leaves them out
+        #[arg(long)]
+        dev_services: Option<String>,
+        /// Comma-separated services that build from the local context in the override file
+        #[arg(long)]
+        dev_build: Option<String>,
+        /// Build argument per service (e.g., "web:VERSION=1.0"); the service builds from "."
+        #[arg(long)]
+        build_arg: Option<String>,
+        /// Multi-stage build target per service (e.g., "web:runtime")
+        #[arg(long)]
+        build_target: Option<String>,
+        /// CPU limit per service (e.g., "web:0.5,worker:2")
+        #[arg(long)]
+        cpus: Option<String>,
+        /// Memory limit per service (e.g., "web:512m,db:1g")
+        #[arg(long)]
+        memory: Option<String>,
+        /// Relative CPU weight per service (e.g., "worker:512")
+        #[arg(long)]
+        cpu_shares: Option<String>,
+        /// CPUs reserved per service, a soft guarantee below --cpus (e.g., "web:0.25")
+        #[arg(long)]
+        cpu_reservation: Option<String>,
+        /// Memory reserved per service, a soft guarantee below --memory (e.g., "web:256m")
+        #[arg(long)]
+        mem_reservation: Option<String>,
+        /// Render limits under `deploy:` (swarm) or as top-level `cpus`/`mem_limit` keys
+        #[arg(long, value_enum, default_value_t = LimitsStyle::Deploy)]
+        limits_style: LimitsStyle,
+        /// Swarm restart policy per service as service:CONDITION[:MAX_ATTEMPTS[:WINDOW[:DELAY]]]
+        /// (e.g., "worker:on-failure:5:10s")
+        #[arg(long)]
+        restart_policy: Option<String>,
+        /// Top-level config with inline content, as name:FILE (read from the file) or name:TEXT; repeatable
+        #[arg(long = "config-inline", value_name = "NAME:FILE_OR_TEXT")]
+        config_inline: Vec<String>,
+        /// Compose project name rendered as the top-level `name:` (defaults to the directory name)
+        #[arg(long, alias = "compose-name")]
+        project_name: Option<String>,
+        /// Compose file pulled in through the top-level `include:` (compose 2.20+); repeatable
+        #[arg(long = "include", value_name = "PATH")]
+        includes: Vec<String>,
+        /// Precede each service with a "# name: image" comment
+        #[arg(long)]
+        annotate: bool,
+        /// List services after the services they depend on instead of in --services order
+        #[arg(long)]
+        sort_services: bool,
+        /// Force the `depends_on` form (default: short, or long when a dependency has a condition)
+        #[arg(long, value_enum)]
+        depends_on_style: Option<DependsOnStyle>,
+        /// Write one compose file per service group instead of a single file
+        #[arg(long, value_enum)]
+        split_by: Option<SplitBy>,
+        /// Group per service for --split-by (e.g., "db:data,prometheus:observability"); others go to "app"
+        #[arg(long, requires = "split_by")]
+        service_group: Option<String>,
+        /// Comma-separated services whose common environment is factored into an `x-common-env` anchor
+        #[arg(long)]
+        share_env: Option<String>,
+        /// Extension field, top-level ("x-common:{...}") or per service ("web:x-owner:team-a"); repeatable
+        #[arg(long = "x", value_name = "[SERVICE:]x-NAME:VALUE")]
+        x_extensions: Vec<String>,
+        /// Comma-separated services whose root filesystem is mounted read-only
+        #[arg(long)]
+        read_only: Option<String>,
+        /// Security options per service (e.g., "web:no-new-privileges:true")
+        #[arg(long)]
+        security_opt: Option<String>,
+        /// Linux capabilities to add per service (e.g., "web:NET_ADMIN")
+        #[arg(long)]
+        cap_add: Option<String>,
+        /// Linux capabilities to drop per service (e.g., "web:ALL")
+        #[arg(long)]
+        cap_drop: Option<String>,
+        /// Comma-separated services that run an init process (tini) as PID 1
+        #[arg(long)]
+        init: Option<String>,
+        /// Comma-separated services that get `stdin_open` and `tty` (like `docker run -it`)
+        #[arg(long)]
+        interactive: Option<String>,
+        /// User (uid[:gid]) each service runs as (e.g., "web:1000:1000")
+        #[arg(long)]
+        service_user: Option<String>,
+        /// Working directory per service (e.g., "web:/srv/app")
+        #[arg(long)]
+        working_dir: Option<String>,
+        /// Container hostname per service (e.g., "db:database")
+        #[arg(long)]
+        hostname: Option<String>,
+        /// Network mode per service (e.g., "web:host,sidecar:service:web")
+        #[arg(long)]
+        network_mode: Option<String>,
+        /// Size of /dev/shm per service (e.g., "chrome:1gb")
+        #[arg(long)]
+        shm_size: Option<String>,
+        /// `docker compose watch` rule per service as ACTION:PATH[:TARGET] (e.g., "web:sync:./src:/app/src")
+        #[arg(long)]
+        watch: Option<String>,
+        /// When compose pulls each service's image: always, never, missing or build (e.g., "web:always")
+        #[arg(long)]
+        pull_policy: Option<String>,
+    },
+    /// Generate a docker-bake.hcl with customizable targets
+    Bake {
+        /// Output filename
+        #[arg(short, long, default_value = "docker-bake.hcl")]
+        output: String,
+        /// Group name for the targets
+        #[arg(long, default_value = "default")]
+        group: String,
+        /// Comma-separated list of target names (e.g., "api,worker,scheduler")
+        #[arg(long)]
+        targets: Option<String>,
+        /// Comma-separated list of contexts for each target (e.g., "./api,./worker,./scheduler")
+        #[arg(long)]
+        contexts: Option<String>,
+        /// Comma-separated list of Dockerfile paths (e.g., "./api/Dockerfile,./worker/Dockerfile")
+        #[arg(long)]
+        dockerfiles: Option<String>,
+        /// Dockerfile embedded in a target as TARGET:FILE_OR_TEXT (read from the file, else taken as
+        /// the content); replaces that target's --dockerfiles entry; repeatable
+        #[arg(long = "dockerfile-inline", value_name = "TARGET:FILE_OR_TEXT")]
+        dockerfile_inline: Vec<String>,
+        /// Comma-separated list of tags for each target (e.g., "api:latest,worker:latest").
+        /// Use `target=tag1|tag2` to give one target several tags (e.g., "api=latest|v1.2")
+        #[arg(long)]
+        tags: Option<String>,
+        /// Build without cache: bare for every target, or a comma-separated list of targets
+        #[arg(long, num_args = 0..=1, value_name = "TARGETS")]
+        no_cache: Option<Option<String>>,
+        /// Always pull base images: bare for every target, or a comma-separated list of targets
+        #[arg(long, num_args = 0..=1, value_name = "TARGETS")]
+        pull: Option<Option<String>>,
+        /// Export destination as `target:type=...` (e.g., "api:type=registry"); repeatable.
+        /// Without a `target:` prefix the output applies to every target
+        #[arg(long = "output-type", value_name = "SPEC")]
+        output_types: Vec<String>,
+        /// Build secret as `[target:]id=...,env=VAR` or `[target:]id=...,src=FILE`
+        /// (e.g., "api:id=token,env=REGISTRY_TOKEN"); repeatable. Without a target prefix the
+        /// secret applies to every target
+        #[arg(long = "secret", value_name = "SPEC")]
+        secrets: Vec<String>,
+        /// OCI annotation as `[target:][level,...:]key=value`; repeatable
+        /// (e.g., "api:index,manifest:org.opencontainers.image.source=https://github.com/me/api").
+        /// Without a target prefix the annotation applies to every target
+        #[arg(long = "annotation", value_name = "ANNOTATION")]
+        annotations: Vec<String>,
+        /// HCL variable with a default value, referenced as ${NAME} in targets (e.g., "TAG=latest"); repeatable
+        #[arg(long = "var", value_name = "NAME=DEFAULT")]
+        variables: Vec<String>,
+        /// Add OCI revision/source labels from the current git checkout to every target
+        #[arg(long)]
+        labels_from_git: bool,
+        /// Comma-separated platforms every target builds for (e.g., "linux/amd64,linux/arm64")
+        #[arg(long, conflicts_with_all = ["amd64", "arm64"])]
+        platforms: Option<String>,
+        /// Build for linux/amd64 (combine with --arm64 for both)
+        #[arg(long)]
+        amd64: bool,
+        /// Build for linux/arm64 (combine with --amd64 for both)
+        #[arg(long)]
+        arm64: bool,
+        /// Emit tighter HCL: single-line arrays, no alignment padding or blank lines
+        #[arg(long)]
+        compact: bool,
+    },
+    /// Generate a development container configuration
+    Devcontainer {
+        /// Container name
+        #[arg(long, default_value = "Dev Container")]
+        name: String,
+        /// Dockerfile path
+        #[arg(long, default_value = "./Dockerfile")]
+        dockerfile: String,
+        /// Compose file to run the dev container from instead of building the Dockerfile
+        #[arg(long, requires = "service", conflicts_with = "dockerfile")]
+        compose_file: Option<String>,
+        /// Compose service VS Code attaches to (with --compose-file)
+        #[arg(long, requires = "compose_file")]
+        service: Option<String>,
+        /// Where the project lives inside the compose service (with --compose-file)
+        #[arg(long, default_value = "/app")]
+        workspace_folder: String,
+        /// Remote user name
+        #[arg(long, default_value = "vscode")]
+        remote_user: String,
+        /// Comma-separated VSCode extensions
+        #[arg(long, default_value = "ms-azuretools.vscode-docker,rust-lang.rust-analyzer")]
+        extensions: String,
+        /// Output filename
+        #[arg(short, long, default_value = "devcontainer.json")]
+        output: String,
+        /// VS Code setting merged over the defaults (e.g., "editor.tabSize=2"); repeatable
+        #[arg(long = "setting", value_name = "KEY=VALUE")]
+        settings: Vec<String>,
+        /// Extra mount (e.g., "source=/var/run/docker.sock,target=/var/run/docker.sock,type=bind"); repeatable
+        #[arg(long = "mount", value_name = "SPEC")]
+        mounts: Vec<String>,
+        /// Extra `docker run` arguments, split on whitespace (e.g., "--gpus all"); repeatable
+        #[arg(long = "run-arg", value_name = "ARGS", allow_hyphen_values = true)]
+        run_args: Vec<String>,
+        /// Environment variable set on the container (e.g., "RUST_LOG=debug"); repeatable
+        #[arg(long = "container-env", value_name = "KEY=VALUE")]
+        container_env: Vec<String>,
+        /// Emit single-line JSON
+        #[arg(long)]
+        compact: bool,
+    },
+    /// Generate a complete development environment
+    Init {
+        /// Project name
+        #[arg(long, default_value = "myproject")]
+        name: String,
+        /// Programming language/framework (e.g., python, node, rust)
+        #[arg(long, default_value = "python")]
+        language: String,
+        /// Database type (e.g., postgres, mysql, mongodb)
+        #[arg(long)]
+        database: Option<String>,
+        /// Extra services built from their own Dockerfile.NAME, as NAME[=COMMAND] (e.g., "worker=python worker.py").
+        /// They share the app's image setup and dependencies but run COMMAND instead
+        #[arg(long)]
+        services_with_build: Option<String>,
+        /// Comma-separated packages installed on top of the language defaults (e.g., "libpq-dev")
+        #[arg(long, alias = "append-packages")]
+        extra_packages: Option<String>,
+        /// Let apt-get install recommended packages too (by default it runs with --no-install-recommends)
+        #[arg(long, overrides_with = "no_recommends")]
+        recommends: bool,
+        /// Keep recommended packages out with --no-install-recommends (the default)
+        #[arg(long, overrides_with = "recommends")]
+        no_recommends: bool,
+        /// Additional services (comma-separated, e.g., redis,elasticsearch)
+        #[arg(long)]
+        services: Option<String>,
+        /// Output directory
+        #[arg(short, long, default_value = ".")]
+        output_dir: String,
+        /// Make the app wait for its dependencies' ports before starting (writes wait-for-it.sh)
+        #[arg(long)]
+        wait_for: bool,
+        /// Compose filename to generate: compose.yaml (modern) or docker-compose.yml (legacy)
+        #[arg(long, value_enum, default_value_t = FilenameConvention::Modern)]
+        filename_convention: FilenameConvention,
+        /// Port mapping for the app service
+        #[arg(long, default_value = "8000:8000")]
+        app_port: String,
+        /// Port mapping for the database (defaults to the database's standard port)
+        #[arg(long)]
+        db_port: Option<String>,
+        /// Extra dependencies between the generated services as service:dependency (e.g., "redis:db")
+        #[arg(long)]
+        depends_on: Option<String>,
+        /// Use known-good versions (postgres:16, mysql:8.4, mongo:7.0, redis:7) instead of :latest
+        #[arg(long)]
+        pin_images: bool,
+        /// Tailor the stack for development (source mount, debug port) or production (no source
+        /// mount, restart: always)
+        #[arg(long, value_enum)]
+        environment: Option<Environment>,
+        /// Put a reverse proxy in front of the app on port 80 and write its config file
+        #[arg(long, value_enum)]
+        proxy: Option<Proxy>,
+        /// Add this to every host port so several generated stacks can run side by side
+        #[arg(long, default_value_t = 0)]
+        port_offset: u16,
+        /// Name/email of maintainer
+        #[arg(long, default_value = "Generated <generated@example.com>")]
+        maintainer: String,
+        /// Working directory in container (the project is mounted here)
+        #[arg(long, default_value = "/app")]
+        workdir: String,
+        /// Image name to tag the locally built app image with (e.g., "ghcr.io/me/app:dev")
+        #[arg(long)]
+        image: Option<String>,
+        /// Also write a README.md describing the stack (an existing README is left alone)
+        #[arg(long)]
+        readme: bool,
+        /// Also write .vscode/tasks.json with build/up/down tasks for the compose stack
+        /// (an existing tasks.json is left alone)
+        #[arg(long)]
+        vscode_tasks: bool,
+        /// Add restart: unless-stopped to every service
+        #[arg(long)]
+        production: bool,
+        /// Don't give known images (postgres, mysql, mongo, redis, elasticsearch) their healthcheck,
+        /// so the app no longer waits for them to be healthy
+        #[arg(long)]
+        no_healthchecks: bool,
+        /// Leave out the app_network bridge network so services use compose's default network
+        #[arg(long)]
+        no_default_network: bool,
+        /// File name for the generated Dockerfile (e.g., "Dockerfile.api"); compose, bake and
+        /// devcontainer.json reference it
+        #[arg(long, default_value = "Dockerfile")]
+        dockerfile_name: String,
+        /// Build rust/go images in one stage instead of copying the binary into a slim runtime image
+        #[arg(long)]
+        single_stage: bool,
+        /// Generate an entrypoint.sh for startup tasks and use it as the image's ENTRYPOINT
+        #[arg(long)]
+        entrypoint_script: bool,
+        /// Point devcontainer.json at the generated compose stack instead of the Dockerfile
+        #[arg(long)]
+        devcontainer_compose: bool,
+        /// Replace the placeholder database passwords with random ones
+        #[arg(long)]
+        generate_passwords: bool,
+        /// Derive --generate-passwords values from this seed so they can be regenerated (dev only)
+        #[arg(long, requires = "generate_passwords")]
+        seed: Option<String>,
+        /// Move service environment values into .env and write a .env.example with secrets blanked
+        #[arg(long)]
+        env_example: bool,
+        /// Keep the source bind mount and a hot-reload command in a compose override file
+        #[arg(long)]
+        dev_override: bool,
+        /// Replace the app's source bind mount with `develop.watch` rules for `docker compose watch`
+        #[arg(long, conflicts_with = "dev_override")]
+        watch: bool,
+        /// Also write a docker-bake.hcl with a target for every service built from source
+        #[arg(long)]
+        bake: bool,
+        /// Run `git init` in the output directory and commit the generated files
+        #[arg(long)]
+        git_init: bool,
+        /// Allow --git-init inside an existing git repository
+        #[arg(long)]
+        force: bool,
+    },
+    /// Generate every file described by one project spec file
+    Project {
+        /// YAML (or JSON) file with optional `dockerfile`, `compose`, `devcontainer` and `bake` sections
+        #[arg(long, value_name = "FILE")]
+        from_file: String,
+        /// Output directory
+        #[arg(short, long, default_value = ".")]
+        output_dir: String,
+    },
+    /// Convert the compose section of a project spec file into a Nomad job
+    Nomad {
+        /// YAML (or JSON) project spec file whose `compose` section is converted
+        #[arg(long, value_name = "FILE")]
+        from_file: String,
+        /// Job (and group) name (default: the compose project name, else "app")
+        #[arg(long)]
+        job: Option<String>,
+        /// Datacenter the job runs in
+        #[arg(long, default_value = "dc1")]
+        datacenter: String,
+        /// Output filename
+        #[arg(short, long, default_value = "job.nomad.hcl")]
+        output: String,
+    },
+    /// Inspect the built-in templates
+    Templates {
+        #[command(subcommand)]
+        action: TemplatesAction,
+    },
+    /// Remove the files `init` generated
+    Clean {
+        /// Directory `init` wrote into
+        #[arg(short, long, default_value = ".")]
+        output_dir: String,
+        /// Delete without asking for confirmation
+        #[arg(long)]
+        force: bool,
+    },
+    /// Lint an existing docker-compose file or Dockerfile
+    Validate {
+        /// File to check (treated as a Dockerfile when its name contains "Dockerfile")
+        file: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum TemplatesAction {
+    /// List the built-in templates
+    List,
+    /// Print a template's raw source
+    Show {
+        /// Template name as shown by `templates list` (e.g., "Dockerfile")
+        name: String,
+    },
+}
+
+/// Builds the error returned for malformed or inconsistent command-line input.
+fn invalid_input(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+/// Replaces `${NAME}` and `${NAME:-default}` references with values from the host environment.
+/// An undefined variable without a default is an error rather than an empty string.
+fn expand_host_env(value: &str) -> io::Result<String> {
+    let mut expanded = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        let Some(len) = rest[start + 2..].find('}') else {
+            return Err(invalid_input(format!("unterminated variable reference in '{}'", value)));
+        };
+        let reference = &rest[start + 2..start + 2 + len];
+        let (name, default) = match reference.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (reference, None),
+        };
+        match (std::env::var(name), default) {
+            (Ok(v), _) => expanded.push_str(&v),
+            (Err(_), Some(default)) => expanded.push_str(default),
+            (Err(_), None) => {
+                return Err(invalid_input(format!("environment variable '{}' is not set (referenced by --expand-env)", name)));
+            }
+        }
+        rest = &rest[start + 2 + len + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Splits a comma-separated flag value into trimmed, non-empty items.
+fn parse_list(input: &str) -> Vec<String> {
+    input
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// True for a bind mount of the build context itself (`./:/app` for context `.`).
+fn is_source_mount(volume: &str, context: &str) -> bool {
+    volume
+        .split_once(':')
+        .is_some_and(|(host, _)| host.starts_with('.') && normalize_context(host) == normalize_context(context))
+}
+
+/// Tailors assembled services to `environment`. Services built from source get a bind mount
+/// of their context in `dev` and lose it in `prod`; `prod` also drops `dev_services` (and
+/// dependencies on them) and gives every service without a restart policy `restart: always`.
+fn apply_environment(services: &mut Vec<ServiceSpec>, environment: Environment, dev_services: &[String]) {
+    match environment {
+        Environment::Dev => {
+            for service in services.iter_mut() {
+                let Some(build) = &service.build else { continue };
+                if !service.volumes.iter().any(|v| is_source_mount(v, &build.context)) {
+                    let target = service.working_dir.as_deref().unwrap_or("/app");
+                    let host = if build.context == "." { "./".to_string() } else { build.context.clone() };
+                    service.volumes.push(format!("{}:{}", host, target));
+                }
+            }
+        }
+        Environment::Prod => {
+            services.retain(|s| !dev_services.contains(&s.name));
+            for service in services.iter_mut() {
+                service.depends_on.retain(|d| !dev_services.contains(&d.service));
+                if let Some(build) = &service.build {
+                    let context = build.context.clone();
+                    service.volumes.retain(|v| !is_source_mount(v, &context));
+                }
+                service.restart.get_or_insert_with(|| "always".to_string());
+            }
+        }
+    }
+}
+
+/// Debugger port published for the app in `init --environment dev`.
+fn debug_port(language: &str) -> Option<&'static str> {
+    match language {
+        // debugpy
+        "python" => Some("5678:5678"),
+        // node --inspect
+        "node" => Some("9229:9229"),
+        // delve
+        "go" => Some("2345:2345"),
+        _ => None,
+    }
+}
+
+/// Compose project names are lowercase letters, digits, `-` and `_`, starting with a letter or digit.
+fn validate_project_name(name: &str) -> io::Result<String> {
+    let valid = name.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if valid {
+        Ok(name.to_string())
+    } else {
+        Err(invalid_input(format!(
+            "--project-name '{}' must use lowercase letters, digits, '-' and '_', starting with a letter or digit; try '{}'",
+            name,
+            sanitize_project_name(name)
+        )))
+    }
+}
+
+/// Turns a free-form name (`My App`) into a valid compose project name (`my-app`).
+fn sanitize_project_name(name: &str) -> String {
+    let name: String = name
+        .to_ascii_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '-' })
+        .collect();
+    name.trim_start_matches(['-', '_']).to_string()
+}
+
+/// Parses `--tags` for bake targets. Entries are either positional (`api:latest`, matched
+/// to targets by index) or keyed (`api=latest|v1.2`). Both forms accept several
+/// `|`-separated tags; keyed values without a `:` are prefixed with the target name.
+fn parse_bake_tags(input: &str, targets: &[String]) -> io::Result<HashMap<String, Vec<String>>> {
+    let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+    for (i, entry) in parse_list(input).iter().enumerate() {
+        let (target, values) = match entry.split_once('=') {
+            Some((target, values)) => {
+                let target = target.trim();
+                if !targets.iter().any(|t| t == target) {
+                    return Err(invalid_input(format!("--tags refers to unknown target '{}'", target)));
+                }
+                let values = values
+                    .split('|')
+                    .map(str::trim)
+                    .filter(|v| !v.is_empty())
+                    .map(|v| if v.contains(':') { v.to_string() } else { format!("{}:{}", target, v) })
+                    .collect::<Vec<_>>();
+                (target.to_string(), values)
+            }
+            None => {
+                let Some(target) = targets.get(i) else {
+                    warning!("ignoring tag '{}' with no matching target", entry);
+                    continue;
+                };
+                let values = entry.split('|').map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+                (target.clone(), values.collect())
+            }
+        };
+        tags.entry(target).or_default().extend(values);
+    }
+    Ok(tags)
+}
+
+/// Resolves a per-target switch: absent selects nothing, a bare flag selects every
+/// target and a comma-separated value selects just those targets.
+fn selected_targets(flag: Option<Option<String>>, targets: &[String], flag_name: &str) -> io::Result<HashSet<String>> {
+    match flag {
+        None => Ok(HashSet::new()),
+        Some(None) => Ok(targets.iter().cloned().collect()),
+        Some(Some(list)) => {
+            let selected: HashSet<String> = parse_list(&list).into_iter().collect();
+            if let Some(unknown) = selected.iter().find(|name| !targets.contains(name)) {
+                return Err(invalid_input(format!("{} refers to unknown target '{}'", flag_name, unknown)));
+            }
+            Ok(selected)
+        }
+    }
+}
+
+/// Checks that a bake block label is an HCL identifier (`[A-Za-z_][A-Za-z0-9_-]*`),
+/// suggesting a fixed-up name when it isn't.
+fn validate_hcl_identifier(name: &str, kind: &str) -> io::Result<()> {
+    let mut chars = name.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if valid {
+        return Ok(());
+    }
+
+    let mut suggestion: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+    if !suggestion.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        suggestion.insert(0, '_');
+    }
+    Err(invalid_input(format!(
+        "bake {} name '{}' is not a valid HCL identifier (letters, digits, '_' and '-', not starting with a digit); try '{}'",
+        kind, name, suggestion
+    )))
+}
+
+const BAKE_OUTPUT_TYPES: &[&str] = &["registry", "image", "local", "tar", "oci", "docker", "cacheonly"];
+
+/// Checks that a bake output spec is a list of `key=value` pairs including a known `type=`.
+fn validate_bake_output(spec: &str) -> io::Result<()> {
+    let invalid = |reason: String| {
+        invalid_input(format!("invalid output '{}': {}", spec, reason))
+    };
+
+    let mut output_type = None;
+    for pair in spec.split(',') {
+        let Some((key, value)) = pair.split_once('=') else {
+            return Err(invalid(format!("'{}' is not a key=value pair", pair)));
+        };
+        if key.trim() == "type" {
+            output_type = Some(value.trim());
+        }
+    }
+    match output_type {
+        None => Err(invalid("missing type=...".to_string())),
+        Some(t) if !BAKE_OUTPUT_TYPES.contains(&t) => Err(invalid(format!(
+            "unknown type '{}' (expected one of: {})",
+            t,
+            BAKE_OUTPUT_TYPES.join(", ")
+        ))),
+        Some(_) => Ok(()),
+    }
+}
+
+/// Checks a bake secret such as `id=token,env=REGISTRY_TOKEN` or `id=npmrc,src=.npmrc`.
+fn validate_bake_secret(spec: &str) -> io::Result<()> {
+    let invalid = |reason: String| invalid_input(format!("invalid secret '{}': {}", spec, reason));
+
+    let mut id = None;
+    for pair in spec.split(',') {
+        let Some((key, value)) = pair.split_once('=') else {
+            return Err(invalid(format!("'{}' is not a key=value pair", pair)));
+        };
+        match (key.trim(), value.trim()) {
+            (_, "") => return Err(invalid(format!("'{}' has no value", key.trim()))),
+            ("id", value) => id = Some(value),
+            ("type", "env" | "file") | ("env" | "src", _) => (),
+            ("type", other) => return Err(invalid(format!("unknown type '{}' (expected env or file)", other))),
+            (other, _) => return Err(invalid(format!("unknown key '{}' (expected id, type, env or src)", other))),
+        }
+    }
+    if id.is_none() {
+        return Err(invalid("missing id=...".to_string()));
+    }
+    Ok(())
+}
+
+/// Parses repeated `target:spec` values (`--output-type`, `--secret`) into per-target lists,
+/// checking each spec with `validate`. Entries without a `target:` prefix apply to every target.
+fn parse_bake_entries(
+    entries: &[String],
+    targets: &[String],
+    flag: &str,
+    validate: fn(&str) -> io::Result<()>,
+) -> io::Result<HashMap<String, Vec<String>>> {
+    let mut parsed: HashMap<String, Vec<String>> = HashMap::new();
+    for entry in entries {
+        let (selected, spec) = match entry.split_once(':') {
+            Some((target, spec)) if !target.contains('=') => {
+                if !targets.iter().any(|t| t == target) {
+                    return Err(invalid_input(format!("{} refers to unknown target '{}'", flag, target)));
+                }
+                (vec![target.to_string()], spec)
+            }
+            _ => (targets.to_vec(), entry.as_str()),
+        };
+        validate(spec)?;
+        for target in selected {
+            parsed.entry(target).or_default().push(spec.to_string());
+        }
+    }
+    Ok(parsed)
+}
+
+/// Parses a comma-separated list of service names, checking each one exists.
+fn parse_service_names(input: &str, services: &[String], flag: &str) -> io::Result<Vec<String>> {
+    let names = parse_list(input);
+    if let Some(unknown) = names.iter().find(|name| !services.contains(name)) {
+        return Err(invalid_input(format!("{} refers to unknown service '{}'", flag, unknown)));
+    }
+    Ok(names)
+}
+
+/// Assigns `--ports`/`--volumes` values to services, either by position (`80:80,5432:5432`) or by
+/// name (`web=80:80;443:443,db=5432:5432`). `;` separates several values for one service.
+fn assign_per_service(input: Option<&str>, services: &[String], flag: &str) -> io::Result<HashMap<String, Vec<String>>> {
+    let entries = input.map(parse_list).unwrap_or_default();
+    let split = |values: &str| -> Vec<String> {
+        values.split(';').map(str::trim).filter(|v| !v.is_empty()).map(String::from).collect()
+    };
+    let mut assigned: HashMap<String, Vec<String>> = HashMap::new();
+
+    if entries.iter().any(|entry| entry.contains('=')) {
+        for entry in &entries {
+            let Some((service, values)) = entry.split_once('=') else {
+                return Err(invalid_input(format!(
+                    "{} mixes service=value and positional entries; give every entry as service=value (got '{}')",
+                    flag, entry
+                )));
+            };
+            let service = service.trim();
+            if !services.iter().any(|s| s == service) {
+                return Err(invalid_input(format!("{} refers to unknown service '{}'", flag, service)));
+            }
+            assigned.entry(service.to_string()).or_default().extend(split(values));
+        }
+        return Ok(assigned);
+    }
+
+    if entries.len() > services.len() {
+        return Err(invalid_input(format!(
+            "{} has {} entries for {} service(s); use service=value entries to say which service each belongs to",
+            flag,
+            entries.len(),
+            services.len()
+        )));
+    }
+    if !entries.is_empty() && entries.len() < services.len() {
+        warning!(
+            "{} has {} entries for {} service(s), matched by position; {} get none",
+            flag,
+            entries.len(),
+            services.len(),
+            quoted_list(&services[entries.len()..])
+        );
+    }
+    for (service, values) in services.iter().zip(&entries) {
+        assigned.insert(service.clone(), split(values));
+    }
+    Ok(assigned)
+}
+
+/// Parses `--config-inline name:FILE_OR_TEXT` entries. An existing file is read (and must be
+/// UTF-8); anything else is taken as the content itself.
+fn parse_inline_configs(entries: &[String]) -> io::Result<BTreeMap<String, InlineConfig>> {
+    let mut configs = BTreeMap::new();
+    for entry in entries {
+        let Some((name, value)) = entry.split_once(':') else {
+            return Err(invalid_input(format!("--config-inline expects name:FILE_OR_TEXT, got '{}'", entry)));
+        };
+        let name = name.trim();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')) {
+            return Err(invalid_input(format!("--config-inline name '{}' may only use letters, digits, '.', '-' and '_'", name)));
+        }
+        let content = if Path::new(value).is_file() {
+            let bytes = std::fs::read(value)?;
+            String::from_utf8(bytes)
+                .map_err(|_| invalid_input(format!("--config-inline {}: {} is not valid UTF-8", name, value)))?
+        } else {
+            value.to_string()
+        };
+        if configs.insert(name.to_string(), InlineConfig::new(&content)).is_some() {
+            return Err(invalid_input(format!("--config-inline defines '{}' more than once", name)));
+        }
+    }
+    Ok(configs)
+}
+
+/// Parses comma-separated `service:value` entries, checking each service exists.
+fn parse_service_values(input: &str, services: &[String], flag: &str) -> io::Result<Vec<(String, String)>> {
+    parse_list(input)
+        .into_iter()
+        .map(|entry| {
+            let Some((service, value)) = entry.split_once(':') else {
+                return Err(invalid_input(format!("{} expects service:value, got '{}'", flag, entry)));
+            };
+            let service = service.trim();
+            if !services.iter().any(|s| s == service) {
+                return Err(invalid_input(format!("{} refers to unknown service '{}'", flag, service)));
+            }
+            Ok((service.to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Parses `--x` entries: `x-name:value` for the top level or `service:x-name:value` for one
+/// service. Values are read as JSON when they parse (`{"a":1}`, `true`) and kept as strings otherwise.
+fn parse_x_extensions(
+    entries: &[String],
+    services: &[String],
+) -> io::Result<Vec<(Option<String>, String, serde_json::Value)>> {
+    entries
+        .iter()
+        .map(|entry| {
+            let (service, field) = match entry.split_once(':') {
+                Some((service, field)) if !entry.starts_with("x-") => (Some(service.trim()), field),
+                _ => (None, entry.as_str()),
+            };
+            if let Some(service) = service {
+                if !services.iter().any(|s| s == service) {
+                    return Err(invalid_input(format!("--x refers to unknown service '{}'", service)));
+                }
+            }
+            let Some((key, value)) = field.split_once(':').filter(|(key, _)| key.starts_with("x-")) else {
+                return Err(invalid_input(format!("--x expects [service:]x-name:value, got '{}'", entry)));
+            };
+            let value = value.trim();
+            let value = serde_json::from_str(value).unwrap_or_else(|_| json!(value));
+            Ok((service.map(String::from), key.trim().to_string(), value))
+        })
+        .collect()
+}
+
+/// Reads a package manifest: one package per line, `#` starts a comment.
+fn read_packages_file(path: &str) -> io::Result<Vec<String>> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("cannot read packages file {}: {}", path, e)))?;
+    Ok(contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Platforms selected by the `--amd64`/`--arm64` shortcuts.
+fn shortcut_platforms(amd64: bool, arm64: bool) -> Vec<String> {
+    [(amd64, "linux/amd64"), (arm64, "linux/arm64")]
+        .into_iter()
+        .filter(|(selected, _)| *selected)
+        .map(|(_, platform)| platform.to_string())
+        .collect()
+}
+
+/// Drops trailing slashes from a build context (`./` -> `.`, `app/` -> `app`).
+fn normalize_context(context: &str) -> String {
+    let trimmed = context.trim().trim_end_matches('/');
+    match trimmed {
+        "" if context.trim().starts_with('/') => "/".to_string(),
+        "" => ".".to_string(),
+        _ => trimmed.to_string(),
+    }
+}
+
+/// The Dockerfile path as a build config expects it: relative to the context.
+fn dockerfile_in_context(dockerfile: &str, context: &str) -> String {
+    let path = Path::new(dockerfile);
+    let path = path.strip_prefix(".").unwrap_or(path);
+    if context == "." {
+        return path.display().to_string();
+    }
+    match path.strip_prefix(Path::new(context).strip_prefix(".").unwrap_or(Path::new(context))) {
+        Ok(relative) => relative.display().to_string(),
+        Err(_) => {
+            // Outside the context: climb out of it so the path still resolves
+            let depth = Path::new(context).components().filter(|c| matches!(c, std::path::Component::Normal(_))).count();
+            format!("{}{}", "../".repeat(depth), path.display())
+        }
+    }
+}
+
+/// Rewrites `image[:tag][@digest]` to `image[:tag]@digest` after checking the digest format.
+fn pin_digest(image: &str, digest: &str) -> io::Result<String> {
+    let digest = digest.trim();
+    let valid = digest
+        .strip_prefix("sha256:")
+        .is_some_and(|hex| hex.len() == 64 && hex.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c)));
+    if !valid {
+        return Err(invalid_input(format!(
+            "invalid digest '{}': expected sha256: followed by 64 lowercase hex characters",
+            digest
+        )));
+    }
+    let image = image.split('@').next().unwrap_or(image);
+    Ok(format!("{}@{}", image, digest))
+}
+
+/// Looks `image` up in a lockfile of `IMAGE DIGEST` lines (`#` starts a comment).
+/// An untagged image also matches its `:latest` entry.
+fn locked_digest(lockfile: &str, image: &str) -> io::Result<String> {
+    let contents = std::fs::read_to_string(lockfile)
+        .map_err(|e| io::Error::new(e.kind(), format!("cannot read lockfile {}: {}", lockfile, e)))?;
+    let image = image.split('@').next().unwrap_or(image);
+    let has_tag = image.rsplit('/').next().is_some_and(|last| last.contains(':'));
+    let latest = format!("{}:latest", image);
+    contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .find(|(name, _)| *name == image || (!has_tag && *name == latest))
+        .map(|(_, digest)| digest.trim().to_string())
+        .ok_or_else(|| invalid_input(format!("no digest for '{}' in {}", image, lockfile)))
+}
+
+/// Normalizes a `--snapshot-date` (`2024-01-01` or `20240101T000000Z`) to the
+/// timestamp form snapshot.debian.org expects.
+fn snapshot_timestamp(date: &str) -> io::Result<String> {
+    let digits: String = date.chars().filter(|c| *c != '-').collect();
+    let valid = match digits.len() {
+        8 => digits.chars().all(|c| c.is_ascii_digit()),
+        16 => {
+            digits[..8].chars().all(|c| c.is_ascii_digit())
+                && &digits[8..9] == "T"
+                && digits[9..15].chars().all(|c| c.is_ascii_digit())
+                && digits.ends_with('Z')
+        }
+        _ => false,
+    };
+    if !valid {
+        return Err(invalid_input(format!(
+            "--snapshot-date expects YYYY-MM-DD or YYYYMMDDTHHMMSSZ, got '{}'",
+            date
+        )));
+    }
+    Ok(if digits.len() == 8 { format!("{}T000000Z", digits) } else { digits })
+}
+
+/// Checks a byte size such as `512m`, `1gb` or `1048576`.
+fn validate_byte_size(value: &str) -> bool {
+    let lower = value.to_ascii_lowercase();
+    let digits = lower.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let unit = &lower[digits.len()..];
+    !digits.is_empty()
+        && digits.chars().all(|c| c.is_ascii_digit())
+        && ["", "b", "k", "kb", "m", "mb", "g", "gb"].contains(&unit)
+}
+
+/// Parses the `--cpus`, `--memory`, `--cpu-shares`, `--cpu-reservation` and `--mem-reservation`
+/// flags into per-service limits.
+fn parse_resource_limits(
+    cpus: Option<&str>,
+    memory: Option<&str>,
+    cpu_shares: Option<&str>,
+    cpu_reservation: Option<&str>,
+    mem_reservation: Option<&str>,
+    services: &[String],
+) -> io::Result<HashMap<String, ResourceLimits>> {
+    let mut limits: HashMap<String, ResourceLimits> = HashMap::new();
+    let parse_cpus = |flag: &str, value: Option<&str>| -> io::Result<Vec<(String, String)>> {
+        let values = parse_service_values(value.unwrap_or_default(), services, flag)?;
+        for (service, value) in &values {
+            if !value.parse::<f64>().is_ok_and(|c| c > 0.0) {
+                return Err(invalid_input(format!("{} for '{}' must be a positive number, got '{}'", flag, service, value)));
+            }
+        }
+        Ok(values)
+    };
+    let parse_memory = |flag: &str, value: Option<&str>| -> io::Result<Vec<(String, String)>> {
+        let values = parse_service_values(value.unwrap_or_default(), services, flag)?;
+        for (service, value) in &values {
+            if !validate_byte_size(value) {
+                return Err(invalid_input(format!(
+                    "{} for '{}' must be a size like 512m or 1g, got '{}'",
+                    flag, service, value
+                )));
+            }
+        }
+        Ok(values)
+    };
+    for (service, value) in parse_cpus("--cpus", cpus)? {
+        limits.entry(service).or_default().cpus = Some(value);
+    }
+    for (service, value) in parse_memory("--memory", memory)? {
+        limits.entry(service).or_default().memory = Some(value);
+    }
+    for (service, value) in parse_cpus("--cpu-reservation", cpu_reservation)? {
+        limits.entry(service).or_default().reservations.cpus = Some(value);
+    }
+    for (service, value) in parse_memory("--mem-reservation", mem_reservation)? {
+        limits.entry(service).or_default().reservations.memory = Some(value);
+    }
+    for (service, value) in parse_service_values(cpu_shares.unwrap_or_default(), services, "--cpu-shares")? {
+        let Ok(shares) = value.parse::<u32>() else {
+            return Err(invalid_input(format!("--cpu-shares for '{}' must be an integer, got '{}'", service, value)));
+        };
+        limits.entry(service).or_default().cpu_shares = Some(shares);
+    }
+    Ok(limits)
+}
+
+/// Checks a Go-style duration such as `10s`, `1m30s` or `500ms`, as compose expects.
+fn is_valid_duration(value: &str) -> bool {
+    let mut rest = value;
+    while !rest.is_empty() {
+        let number = rest.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+        let digits = &rest[..rest.len() - number.len()];
+        if digits.is_empty() || digits.parse::<f64>().is_err() {
+            return false;
+        }
+        let Some(unit) = ["ns", "us", "ms", "h", "m", "s"].into_iter().find(|u| number.starts_with(u)) else {
+            return false;
+        };
+        rest = &number[unit.len()..];
+    }
+    !value.is_empty()
+}
+
+/// Parses a `--restart-policy` value: `CONDITION[:MAX_ATTEMPTS[:WINDOW[:DELAY]]]`.
+fn parse_restart_policy(service: &str, value: &str) -> io::Result<RestartPolicy> {
+    let fail = |reason: String| {
+        invalid_input(format!(
+            "--restart-policy for '{}': {} (expected CONDITION[:MAX_ATTEMPTS[:WINDOW[:DELAY]]], e.g. on-failure:5:10s)",
+            service, reason
+        ))
+    };
+    let mut parts = value.split(':');
+    let condition = parts.next().unwrap_or_default();
+    if !["none", "on-failure", "any"].contains(&condition) {
+        return Err(fail(format!("condition must be none, on-failure or any, got '{}'", condition)));
+    }
+    let max_attempts = parts
+        .next()
+        .map(|n| n.parse::<u32>().map_err(|_| fail(format!("max attempts must be an integer, got '{}'", n))))
+        .transpose()?;
+    let mut duration = |what: &str| {
+        parts
+            .next()
+            .map(|d| if is_valid_duration(d) { Ok(d.to_string()) } else { Err(fail(format!("{} must be a duration like 10s or 1m30s, got '{}'", what, d))) })
+            .transpose()
+    };
+    let window = duration("window")?;
+    let delay = duration("delay")?;
+    if parts.next().is_some() {
+        return Err(fail(format!("too many fields in '{}'", value)));
+    }
+    Ok(RestartPolicy { condition: condition.to_string(), max_attempts, window, delay })
+}
+
+/// Expands `--service-template NAME{n}:IMAGE` into `count` services, substituting 1..=count for
+/// `{n}` in the name and image. With `port`, the first service publishes it and each further one
+/// the next host port.
+fn expand_service_template(template: &str, count: u16, port: Option<&str>) -> io::Result<Vec<ServiceSpec>> {
+    let Some((name, image)) = template.split_once(':').filter(|(name, image)| !name.is_empty() && !image.is_empty()) else {
+        return Err(invalid_input(format!("--service-template expects NAME{{n}}:IMAGE, got '{}'", template)));
+    };
+    if !name.contains("{n}") {
+        return Err(invalid_input(format!(
+            "--service-template name '{}' needs a {{n}} placeholder to tell the services apart",
+            name
+        )));
+    }
+    if count == 0 {
+        return Err(invalid_input("--count must be at least 1".to_string()));
+    }
+    let port = port.map(port_mapping);
+    (1..=count)
+        .map(|n| {
+            let ports = match &port {
+                Some(port) => vec![shift_host_port(port, n - 1).map_err(|_| {
+                    invalid_input(format!("--template-port {} runs out of host ports by service {}", port, n))
+                })?],
+                None => Vec::new(),
+            };
+            Ok(ServiceSpec {
+                name: name.replace("{n}", &n.to_string()),
+                image: image.replace("{n}", &n.to_string()),
+                ports,
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+/// Values compose accepts for a service's `pull_policy`.
+const PULL_POLICIES: &[&str] = &["always", "never", "missing", "build"];
+
+/// Parses a `--watch` value: `ACTION:PATH[:TARGET]`. The sync actions need a target.
+fn parse_watch_rule(service: &str, value: &str) -> io::Result<WatchRule> {
+    let fail = |reason: String| {
+        invalid_input(format!(
+            "--watch for '{}': {} (expected ACTION:PATH[:TARGET], e.g. sync:./src:/app/src)",
+            service, reason
+        ))
+    };
+    let mut parts = value.splitn(3, ':');
+    let action = parts.next().unwrap_or_default();
+    if !["sync", "rebuild", "sync+restart"].contains(&action) {
+        return Err(fail(format!("action must be sync, rebuild or sync+restart, got '{}'", action)));
+    }
+    let path = parts.next().filter(|p| !p.is_empty()).ok_or_else(|| fail("missing path".to_string()))?;
+    let target = parts.next().filter(|t| !t.is_empty()).map(String::from);
+    if action != "rebuild" && target.is_none() {
+        return Err(fail(format!("'{}' needs a container target path", action)));
+    }
+    Ok(WatchRule { action: action.to_string(), path: path.to_string(), target })
+}
+
+const LINUX_CAPABILITIES: &[&str] = &[
+    "AUDIT_CONTROL", "AUDIT_READ", "AUDIT_WRITE", "BLOCK_SUSPEND", "BPF", "CHECKPOINT_RESTORE",
+    "CHOWN", "DAC_OVERRIDE", "DAC_READ_SEARCH", "FOWNER", "FSETID", "IPC_LOCK", "IPC_OWNER",
+    "KILL", "LEASE", "LINUX_IMMUTABLE", "MAC_ADMIN", "MAC_OVERRIDE", "MKNOD", "NET_ADMIN",
+    "NET_BIND_SERVICE", "NET_BROADCAST", "NET_RAW", "PERFMON", "SETFCAP", "SETGID", "SETPCAP",
+    "SETUID", "SYSLOG", "SYS_ADMIN", "SYS_BOOT", "SYS_CHROOT", "SYS_MODULE", "SYS_NICE",
+    "SYS_PACCT", "SYS_PTRACE", "SYS_RAWIO", "SYS_RESOURCE", "SYS_TIME", "SYS_TTY_CONFIG",
+    "WAKE_ALARM",
+];
+
+/// Accepts `ALL` and known capabilities, with or without the `CAP_` prefix.
+fn is_known_capability(cap: &str) -> bool {
+    let cap = cap.to_ascii_uppercase();
+    let cap = cap.strip_prefix("CAP_").unwrap_or(&cap);
+    cap == "ALL" || LINUX_CAPABILITIES.contains(&cap)
+}
+
+/// Checks a `uid[:gid]` user spec, where each part is numeric or a user/group name.
+fn is_valid_user(user: &str) -> bool {
+    let valid_part = |part: &str| {
+        part.chars().all(|c| c.is_ascii_digit())
+            || (part.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+                && part.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-'))
+    };
+    let mut parts = user.split(':');
+    let uid = parts.next().unwrap_or_default();
+    let gid = parts.next();
+    !uid.is_empty()
+        && valid_part(uid)
+        && gid.is_none_or(|g| !g.is_empty() && valid_part(g))
+        && parts.next().is_none()
+}
+
+/// Strips the registry path and tag from an image reference (`docker.io/library/redis:7` -> `redis`).
+fn image_name(image: &str) -> &str {
+    let name = image.rsplit('/').next().unwrap_or(image);
+    name.split([':', '@']).next().unwrap_or(name)
+}
+
+/// Known-good major versions `Init --pin-images` uses instead of `:latest`.
+fn pinned_image(image: &str) -> Option<String> {
+    let tag = match image_name(image) {
+        "postgres" => "16",
+        "mysql" => "8.4",
+        "mongo" => "7.0",
+        "redis" => "7",
+        _ => return None,
+    };
+    let repository = image.strip_suffix(":latest")?;
+    Some(format!("{}:{}", repository, tag))
+}
+
+/// Canonical healthchecks for well-known images, keyed by image name.
+fn known_healthcheck(image: &str) -> Option<Healthcheck> {
+    let test: &[&str] = match image_name(image) {
+        "postgres" => &["CMD-SHELL", "pg_isready -U $$POSTGRES_USER"],
+        "mysql" => &["CMD", "mysqladmin", "ping", "-h", "localhost"],
+        "mongo" => &["CMD", "mongosh", "--quiet", "--eval", "db.adminCommand('ping')"],
+        "redis" => &["CMD", "redis-cli", "ping"],
+        "elasticsearch" => &["CMD-SHELL", "curl -fs http://localhost:9200/_cluster/health || exit 1"],
+        _ => return None,
+    };
+    Some(Healthcheck {
+        test: test.iter().map(|s| s.to_string()).collect(),
+        interval: "10s".to_string(),
+        timeout: "5s".to_string(),
+        retries: 5,
+    })
+}
+
+fn is_bind_mount(volume: &str) -> bool {
+    matches!(volume.chars().next(), Some('.' | '/' | '~'))
+}
+
+/// Moves dev-only settings out of `services` and returns the override entries that
+/// receive them. Services left with nothing to override are omitted.
+fn split_dev_overrides(
+    services: &mut [ServiceSpec],
+    dev_only: &[DevOnlySetting],
+    dev_build: &[String],
+) -> Vec<ServiceSpec> {
+    services
+        .iter_mut()
+        .filter_map(|service| {
+            let mut dev = ServiceSpec {
+                name: service.name.clone(),
+                ..Default::default()
+            };
+            if dev_only.contains(&DevOnlySetting::Ports) {
+                dev.ports = std::mem::take(&mut service.ports);
+            }
+            if dev_only.contains(&DevOnlySetting::Mounts) {
+                let (binds, named) = std::mem::take(&mut service.volumes)
+                    .into_iter()
+                    .partition(|v| is_bind_mount(v));
+                service.volumes = named;
+                dev.volumes = binds;
+            }
+            if dev_build.contains(&service.name) {
+                dev.build = Some(service.build.take().unwrap_or_else(|| BuildConfig::new(".")));
+            }
+
+            let empty = dev.ports.is_empty() && dev.volumes.is_empty() && dev.build.is_none();
+            (!empty).then_some(dev)
+        })
+        .collect()
+}
+
+/// Splits a compose spec into one file per service group, in order of first appearance.
+/// Networks and `x-` fields go in the first file; the shared-env anchor is repeated in
+/// every file that merges it, since YAML anchors don't reach across files.
+fn split_compose_by_group(
+    spec: DockerComposeSpec,
+    groups: &HashMap<String, String>,
+    output: &str,
+) -> Vec<(String, DockerComposeSpec)> {
+    let mut files: Vec<(String, DockerComposeSpec)> = Vec::new();
+    for service in spec.services {
+        let group = groups.get(&service.name).map_or("app", String::as_str);
+        let path = group_filename(output, group);
+        let index = match files.iter().position(|(p, _)| *p == path) {
+            Some(index) => index,
+            None => {
+                files.push((
+                    path,
+                    DockerComposeSpec {
+                        project_name: spec.project_name.clone(),
+                        limits_style: spec.limits_style,
+                        depends_on_style: spec.depends_on_style,
+                        annotate: spec.annotate,
+                        ..Default::default()
+                    },
+                ));
+                files.len() - 1
+            }
+        };
+        let file = &mut files[index].1;
+        if service.shared_env {
+            file.common_env = spec.common_env.clone();
+        }
+        file.services.push(service);
+    }
+    if let Some((_, first)) = files.first_mut() {
+        first.includes = spec.includes;
+        first.networks = spec.networks;
+        first.x_extensions = spec.x_extensions;
+        first.configs = spec.configs;
+    }
+    files
+}
+
+/// `compose.yaml` + `data` -> `compose.data.yaml`
+fn group_filename(output: &str, group: &str) -> String {
+    let path = Path::new(output);
+    let file_name = match (path.file_stem(), path.extension()) {
+        (Some(stem), Some(ext)) => format!("{}.{}.{}", stem.to_string_lossy(), group, ext.to_string_lossy()),
+        _ => format!("{}.{}", output, group),
+    };
+    path.with_file_name(file_name).display().to_string()
+}
+
+/// Moves the environment entries every `sharing` service has in common out of those
+/// services, marks them as merging the shared anchor and returns the common entries.
+fn share_environment(services: &mut [ServiceSpec], sharing: &[String]) -> Vec<(String, String)> {
+    let mut opted_in = services.iter_mut().filter(|s| sharing.contains(&s.name)).collect::<Vec<_>>();
+    let Some((first, rest)) = opted_in.split_first() else {
+        return Vec::new();
+    };
+    let common: Vec<(String, String)> = first
+        .environment
+        .iter()
+        .filter(|entry| rest.iter().all(|s| s.environment.contains(entry)))
+        .cloned()
+        .collect();
+    if common.is_empty() {
+        return common;
+    }
+    for service in &mut opted_in {
+        service.environment.retain(|entry| !common.contains(entry));
+        service.shared_env = true;
+    }
+    common
+}
+
+const PASSWORD_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+const PASSWORD_LEN: usize = 24;
+
+/// An alphanumeric password for `--generate-passwords`. With a seed it is derived from
+/// HMAC-SHA256(seed, label), so the same seed and label always give the same password;
+/// otherwise the bytes come from the OS CSPRNG.
+fn generate_password(seed: Option<&str>, label: &str) -> io::Result<String> {
+    let mut password = String::with_capacity(PASSWORD_LEN);
+    let mut block = 0u32;
+    while password.len() < PASSWORD_LEN {
+        let bytes: [u8; 32] = match seed {
+            Some(seed) => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(seed.as_bytes()).expect("HMAC accepts keys of any length");
+                mac.update(label.as_bytes());
+                mac.update(&block.to_be_bytes());
+                mac.finalize().into_bytes().into()
+            }
+            None => {
+                let mut bytes = [0u8; 32];
+                getrandom::fill(&mut bytes).map_err(|e| io::Error::other(format!("cannot read random bytes: {}", e)))?;
+                bytes
+            }
+        };
+        block += 1;
+        // Skip the top of the byte range so every character is equally likely
+        let limit = 256 - 256 % PASSWORD_CHARS.len();
+        for byte in bytes.into_iter().filter(|b| (*b as usize) < limit) {
+            if password.len() < PASSWORD_LEN {
+                password.push(PASSWORD_CHARS[byte as usize % PASSWORD_CHARS.len()] as char);
+            }
+        }
+    }
+    Ok(password)
+}
+
+/// Moves service environment values into `.env` entries, leaving `${KEY}` references behind for
+/// compose to interpolate. Keys that aren't valid variable names, or that another service already
+/// set to a different value, stay inline.
+fn extract_env_file(services: &mut [ServiceSpec]) -> Vec<(String, String)> {
+    let mut entries: Vec<(String, String)> = Vec::new();
+    for service in services {
+        for (key, value) in &mut service.environment {
+            let valid = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid {
+                continue;
+            }
+            match entries.iter().find(|(k, _)| k == key) {
+                Some((_, existing)) if existing != value => continue,
+                Some(_) => (),
+                None => entries.push((key.clone(), value.clone())),
+            }
+            *value = format!("${{{}}}", key);
+        }
+    }
+    entries
+}
+
+/// Renders `KEY=value` lines, quoting values dotenv would otherwise split or truncate.
+fn render_env_file(header: &str, entries: &[(String, String)]) -> String {
+    let mut contents = format!("{}\n", header);
+    for (key, value) in entries {
+        if value.contains(|c: char| c.is_whitespace() || c == '#' || c == '"' || c == '\'') {
+            contents.push_str(&format!("{}=\"{}\"\n", key, value.replace('\\', "\\\\").replace('"', "\\\"")));
+        } else {
+            contents.push_str(&format!("{}={}\n", key, value));
+        }
+    }
+    contents
+}
+
+/// Secrets are left blank in `.env.example`; everything else keeps its default.
+fn is_secret_key(key: &str) -> bool {
+    let key = key.to_ascii_uppercase();
+    ["PASSWORD", "SECRET", "TOKEN", "API_KEY"].iter().any(|marker| key.contains(marker))
+}
+
+/// Copies the dependency manifests and installs from them before copying the rest of the
+/// project, so source edits don't invalidate the dependency layer.
+fn source_steps(language: &str) -> Vec<String> {
+    let steps: &[&str] = match language {
+        "python" => &[
+            "COPY requirements.txt .",
+            "RUN pip install --no-cache-dir -r requirements.txt",
+            "COPY . .",
+        ],
+        "node" => &[
+            "COPY package.json package-lock.json* ./",
+            "RUN npm install",
+            "COPY . .",
+        ],
+        // Build the dependencies against a stub main, then the real sources; touch main.rs so
+        // cargo doesn't mistake the stub's build for an up-to-date one
+        "rust" => &[
+            "COPY Cargo.toml Cargo.lock* ./",
+            "RUN mkdir src && echo 'fn main() {}' > src/main.rs && cargo build --release && rm -rf src",
+            "COPY . .",
+            "RUN touch src/main.rs && cargo build --release",
+        ],
+        "go" => &[
+            "COPY go.mod go.sum* ./",
+            "RUN go mod download",
+            "COPY . .",
+            "RUN CGO_ENABLED=0 go build -o /out/app .",
+        ],
+        _ => &["COPY . ."],
+    };
+    steps.iter().map(|step| step.to_string()).collect()
+}
+
+/// Runtime stage for the compiled languages `Init` knows. Go binaries are static and fit a
+/// distroless image, unless a shell script has to run in it.
+fn runtime_stage(language: &str, name: &str, workdir: &str, needs_shell: bool) -> Option<RuntimeStage> {
+    let (base_image, artifact) = match language {
+        "rust" => ("debian:bookworm-slim", format!("{}/target/release/{}", workdir, name)),
+        "go" if needs_shell => ("debian:bookworm-slim", "/out/app".to_string()),
+        "go" => ("gcr.io/distroless/static-debian12", "/out/app".to_string()),
+        _ => return None,
+    };
+    Some(RuntimeStage {
+        base_image: base_image.to_string(),
+        artifact,
+        path: format!("/usr/local/bin/{}", name),
+    })
+}
+
+/// Dev server command that restarts on source changes, for the languages `Init` knows.
+fn reload_command(language: &str, port: &str) -> Option<Vec<String>> {
+    let command = match language {
+        "python" => vec!["uvicorn", "main:app", "--host", "0.0.0.0", "--port", port, "--reload"],
+        "node" => vec!["npx", "nodemon", "index.js"],
+        "rust" => vec!["cargo", "watch", "-x", "run"],
+        "go" => vec!["go", "run", "."],
+        _ => return None,
+    };
+    Some(command.into_iter().map(String::from).collect())
+}
+
+/// `develop.watch` rules for the languages `Init` knows: interpreted sources sync into the
+/// container, while dependency manifests and compiled sources trigger a rebuild.
+fn watch_rules(language: &str, workdir: &str) -> Vec<WatchRule> {
+    let sync = |path: &str| WatchRule { action: "sync".to_string(), path: path.to_string(), target: Some(workdir.to_string()) };
+    let rebuild = |path: &str| WatchRule { action: "rebuild".to_string(), path: path.to_string(), target: None };
+    match language {
+        "python" => vec![sync("."), rebuild("requirements.txt")],
+        "node" => vec![sync("."), rebuild("package.json")],
+        "rust" => vec![rebuild("src"), rebuild("Cargo.toml")],
+        "go" => vec![rebuild(".")],
+        _ => vec![sync(".")],
+    }
+}
+
+/// Expands a bare port (`8080`) into a `host:container` mapping (`8080:8080`).
+fn port_mapping(port: &str) -> String {
+    let port = port.trim();
+    if port.contains(':') {
+        port.to_string()
+    } else {
+        format!("{}:{}", port, port)
+    }
+}
+
+/// Adds `offset` to the host side of a port mapping (`8000:8000` -> `8100:8000`), including
+/// ranges and mappings with a bind address. Container-only mappings are left alone.
+fn shift_host_port(mapping: &str, offset: u16) -> io::Result<String> {
+    let (ports, protocol) = match mapping.split_once('/') {
+        Some((ports, protocol)) => (ports, format!("/{}", protocol)),
+        None => (mapping, String::new()),
+    };
+    let Some((host, container)) = ports.rsplit_once(':') else {
+        return Ok(mapping.to_string());
+    };
+    let (address, host_ports) = match host.rsplit_once(':') {
+        Some((address, port)) => (format!("{}:", address), port),
+        None => (String::new(), host),
+    };
+    let shifted = host_ports
+        .split('-')
+        .map(|port| {
+            port.parse::<u16>()
+                .ok()
+                .and_then(|port| port.checked_add(offset))
+                .map(|port| port.to_string())
+                .ok_or_else(|| invalid_input(format!("--port-offset {} moves host port '{}' out of range", offset, port)))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    Ok(format!("{}{}:{}{}", address, shifted.join("-"), container, protocol))
+}
+
+/// Applies `--depends-on` entries (`web:db,web:redis` or `web:db|redis`), skipping repeats.
+/// Each dependency may carry a condition and `restart`: `web:db:service_healthy:restart`.
+fn add_dependencies(services: &mut [ServiceSpec], input: &str) -> io::Result<()> {
+    for entry in parse_list(input) {
+        let Some((service, deps)) = entry.split_once(':') else {
+            return Err(invalid_input(format!("--depends-on expects service:dependency, got '{}'", entry)));
+        };
+        let Some(spec) = services.iter_mut().find(|s| s.name == service.trim()) else {
+            return Err(invalid_input(format!("--depends-on refers to unknown service '{}'", service)));
+        };
+        for dep in deps.split('|').map(str::trim).filter(|d| !d.is_empty()) {
+            let dep = parse_dependency(dep)?;
+            if !spec.depends_on.iter().any(|d| d.service == dep.service) {
+                spec.depends_on.push(dep);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parses `name[:condition][:restart]`. `restart` alone implies `service_started`.
+fn parse_dependency(input: &str) -> io::Result<Dependency> {
+    let mut parts = input.split(':').map(str::trim);
+    let mut dependency = Dependency::new(parts.next().unwrap_or_default());
+    for part in parts {
+        match part {
+            "restart" => dependency.restart = true,
+            "service_started" | "service_healthy" | "service_completed_successfully" if dependency.condition.is_none() && !dependency.restart => {
+                dependency.condition = Some(part.to_string());
+            }
+            _ => {
+                return Err(invalid_input(format!(
+                    "--depends-on '{}': expected service[:service_started|service_healthy|service_completed_successfully][:restart], got '{}'",
+                    input, part
+                )))
+            }
+        }
+    }
+    if dependency.restart && dependency.condition.is_none() {
+        dependency.condition = Some("service_started".to_string());
+    }
+    Ok(dependency)
+}
+
+/// Standard port mapping for the databases `Init` knows about.
+fn default_db_port(database: &str) -> &'static str {
+    match database {
+        "mysql" => "3306:3306",
+        "mongodb" => "27017:27017",
+        _ => "5432:5432",
+    }
+}
+
+const ANNOTATION_LEVELS: &[&str] = &["manifest", "index", "manifest-descriptor", "index-descriptor"];
+
+/// Checks the `[level,...:]key=value` annotation shape. Levels may carry a
+/// platform qualifier such as `manifest[linux/amd64]`.
+fn validate_annotation(annotation: &str) -> io::Result<()> {
+    let invalid = |reason: &str| invalid_input(format!("invalid annotation '{}': {}", annotation, reason));
+
+    let Some((target, _value)) = annotation.split_once('=') else {
+        return Err(invalid("expected key=value"));
+    };
+    let key = match target.rsplit_once(':') {
+        Some((levels, key)) => {
+            for level in levels.split(',') {
+                let name = level.split('[').next().unwrap_or(level).trim();
+                if !ANNOTATION_LEVELS.contains(&name) {
+                    return Err(invalid(&format!(
+                        "unknown level '{}' (expected one of: {})",
+                        name,
+                        ANNOTATION_LEVELS.join(", ")
+                    )));
+                }
+            }
+            key
+        }
+        None => target,
+    };
+    if key.trim().is_empty() {
+        return Err(invalid("missing key"));
+    }
+    Ok(())
+}
+
+/// Parses repeated `--annotation` values into per-target lists. A leading
+/// `target:` selects one target; anything else applies to every target.
+fn parse_bake_annotations(entries: &[String], targets: &[String]) -> io::Result<HashMap<String, Vec<String>>> {
+    let mut annotations: HashMap<String, Vec<String>> = HashMap::new();
+    for entry in entries {
+        let (selected, annotation) = match entry.split_once(':') {
+            Some((target, annotation)) if targets.iter().any(|t| t == target) => (vec![target.to_string()], annotation),
+            _ => (targets.to_vec(), entry.as_str()),
+        };
+        validate_annotation(annotation)?;
+        for target in selected {
+            annotations.entry(target).or_default().push(annotation.to_string());
+        }
+    }
+    Ok(annotations)
+}
+
+/// Colors are used only on a terminal and never when `NO_COLOR` is set, so piped logs stay plain.
+fn color_enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()) && io::stdout().is_terminal())
+}
+
+/// Wraps `text` in `style` when colors are enabled.
+fn paint(style: Style, text: &str) -> String {
+    if color_enabled() {
+        format!("{}{}{}", style.render(), text, style.render_reset())
+    } else {
+        text.to_string()
+    }
+}
+
+const HEADER: Style = Style::new().bold().fg_color(Some(Color::Ansi(AnsiColor::Cyan)));
+const PROMPT: Style = Style::new().bold();
+const DEFAULT_VALUE: Style = Style::new().fg_color(Some(Color::Ansi(AnsiColor::Yellow)));
+const ERROR: Style = Style::new().fg_color(Some(Color::Ansi(AnsiColor::Red)));
+const SUCCESS: Style = Style::new().bold().fg_color(Some(Color::Ansi(AnsiColor::Green)));
+const DIFF_HEADER: Style = Style::new().bold();
+const DIFF_HUNK: Style = Style::new().fg_color(Some(Color::Ansi(AnsiColor::Cyan)));
+const DIFF_ADDED: Style = Style::new().fg_color(Some(Color::Ansi(AnsiColor::Green)));
+
+/// Prints an interactive section header such as `=== Add Service ===`.
+fn section(title: &str) {
+    println!("\n{}", paint(HEADER, &format!("=== {} ===", title)));
+}
+
+fn prompt(message: &str) -> io::Result<String> {
+    // Highlight the value an empty answer falls back to
+    let message = match message.split_once("(default: ") {
+        Some((text, default)) => format!("{}{}", paint(PROMPT, text), paint(DEFAULT_VALUE, &format!("(default: {}", default))),
+        None => paint(PROMPT, message),
+    };
+    print!("{}: ", message);
+    io::stdout().flush()?;
+    let mut input = String::new();
+    if io::stdin().read_line(&mut input)? == 0 {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "no input available to answer the prompt"));
+    }
+    Ok(input.trim().to_string())
+}
+
+fn select_option(options: &[&str], prompt_msg: &str) -> io::Result<usize> {
+    println!("\n{}", paint(PROMPT, prompt_msg));
+    for (i, opt) in options.iter().enumerate() {
+        println!("{}. {}", i + 1, opt);
+    }
+    
+    loop {
+        let input = prompt("Enter number")?;
+        if let Ok(num) = input.parse::<usize>() {
+            if num > 0 && num <= options.len() {
+                return Ok(num - 1);
+            }
+        }
+        println!("{}", paint(ERROR, &format!("Please enter a number between 1 and {}", options.len())));
+    }
+}
+
+fn confirm(message: &str) -> io::Result<bool> {
+    loop {
+        let input = prompt(&format!("{} (y/n)", message))?.to_lowercase();
+        match input.as_str() {
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => println!("{}", paint(ERROR, "Please enter 'y' or 'n'")),
+        }
+    }
+}
+
+/// One line of the review shown before the interactive flow writes anything: the current value
+/// and how a re-entered answer is stored back into the spec.
+struct ReviewField<'a> {
+    label: String,
+    value: String,
+    set: Box<dyn FnMut(&str) + 'a>,
+}
+
+fn review_field<'a>(label: impl Into<String>, value: impl Into<String>, set: impl FnMut(&str) + 'a) -> ReviewField<'a> {
+    ReviewField { label: label.into(), value: value.into(), set: Box::new(set) }
+}
+
+/// Lists the collected answers by number and lets the user re-enter any of them, until a blank
+/// answer confirms the spec.
+fn review_spec(mut fields: Vec<ReviewField>) -> io::Result<()> {
+    loop {
+        section("Review");
+        for (i, field) in fields.iter().enumerate() {
+            let value = if field.value.is_empty() { "(none)" } else { field.value.as_str() };
+            println!("{}. {}: {}", i + 1, field.label, paint(DEFAULT_VALUE, value));
+        }
+        let input = prompt("Field number to edit (blank to generate)")?;
+        if input.is_empty() {
+            return Ok(());
+        }
+        let Some(field) = input.parse::<usize>().ok().filter(|n| (1..=fields.len()).contains(n)).map(|n| &mut fields[n - 1]) else {
+            println!("{}", paint(ERROR, &format!("Please enter a number between 1 and {}", fields.len())));
+            continue;
+        };
+        let current = if field.value.is_empty() { "none" } else { field.value.as_str() };
+        let value = prompt(&format!("{} (default: {})", field.label, current))?;
+        if !value.is_empty() {
+            (field.set)(&value);
+            field.value = value;
+        }
+    }
+}
+
+/// Parses `KEY=VALUE,KEY2=VALUE2`, dropping entries without `=`.
+fn parse_env_pairs(input: &str) -> Vec<(String, String)> {
+    input
+        .split(',')
+        .filter_map(|pair| pair.split_once('=').map(|(k, v)| (k.trim().to_string(), v.trim().to_string())))
+        .collect()
+}
+
+/// `none` clears an optional answer in the review.
+fn optional_answer(value: &str) -> Option<String> {
+    (!value.eq_ignore_ascii_case("none")).then(|| value.to_string())
+}
+
+fn interactive_cli(out: OutputOptions) -> io::Result<()> {
+    section("Docker Configuration Generator");
+    
+    let options = ["Generate Dockerfile", "Generate Docker Compose", "Generate Dev Container", "Generate Docker Bake", "Generate Complete Environment"];
+    let choice = select_option(&options, "What would you like to generate?")?;
+
+    match choice {
+        0 => {
+            // Dockerfile
+            let base_image = prompt("Base image (default: ubuntu:22.04)")?;
+            let base_image = if base_image.is_empty() { "ubuntu:22.04".to_string() } else { base_image };
+            
+            let maintainer = prompt("Maintainer (default: Generated <generated@example.com>)")?;
+            let maintainer = if maintainer.is_empty() { "Generated <generated@example.com>".to_string() } else { maintainer };
+            
+            let packages = prompt("Packages (comma-separated, default: curl,git)")?;
+            let packages = if packages.is_empty() { "curl,git".to_string() } else { packages };
+            
+            let workdir = prompt("Working directory (default: /app)")?;
+            let workdir = if workdir.is_empty() { "/app".to_string() } else { workdir };
+            
+            let entrypoint = prompt("Entrypoint (default: /bin/bash)")?;
+            let entrypoint = if entrypoint.is_empty() { "/bin/bash".to_string() } else { entrypoint };
+            
+            let output = prompt("Output filename (default: Dockerfile)")?;
+            let mut output = if output.is_empty() { "Dockerfile".to_string() } else { output };
+
+            let mut spec = DockerfileSpec {
+                base_image,
+                maintainer,
+                packages: packages.split(',').map(|s| s.trim().to_string()).collect(),
+                workdir,
+                entrypoint,
+                ..Default::default()
+            };
+            review_spec(vec![
+                review_field("Base image", spec.base_image.clone(), |v| spec.base_image = v.to_string()),
+                review_field("Maintainer", spec.maintainer.clone(), |v| spec.maintainer = v.to_string()),
+                review_field("Packages", spec.packages.join(","), |v| spec.packages = parse_list(v)),
+                review_field("Working directory", spec.workdir.clone(), |v| spec.workdir = v.to_string()),
+                review_field("Entrypoint", spec.entrypoint.clone(), |v| spec.entrypoint = v.to_string()),
+                review_field("Output filename", output.clone(), |v| output = v.to_string()),
+            ])?;
+            let rendered = render_template("Dockerfile", DOCKERFILE_TEMPLATE, &spec).expect("Failed to render Dockerfile");
+            write_to_file(Path::new(&output), &rendered, Some("Dockerfile"), out)?;
+        }
+        1 => {
+            // Docker Compose
+            let mut services = Vec::new();
+            loop {
+                section("Add Service");
+                let name = prompt("Service name")?;
+                let image = prompt("Image (default: latest)")?;
+                let image = if image.is_empty() { format!("{}:latest", name) } else { image };
+                
+                let ports = prompt("Ports (comma-separated, e.g., 80:80,443:443)")?;
+                let ports: Vec<String> = if ports.is_empty() {
+                    vec!["80:80".to_string()]
+                } else {
+                    ports.split(',').map(|s| s.trim().to_string()).collect()
+                };
+
+                let volumes = prompt("Volumes (comma-separated, e.g., ./data:/data)")?;
+                let volumes: Vec<String> = if volumes.is_empty() {
+                    vec!["./data:/data".to_string()]
+                } else {
+                    volumes.split(',').map(|s| s.trim().to_string()).collect()
+                };
+
+                let env_input = prompt("Environment variables (KEY=VALUE,KEY2=VALUE2)")?;
+                let environment = parse_env_pairs(&env_input);
+
+                services.push(ServiceSpec {
+                    name,
+                    image,
+                    ports,
+                    depends_on: Vec::new(),
+                    environment,
+                    volumes,
+                    ..Default::default()
+                });
+
+                if !confirm("Add another service?")? {
+                    break;
+                }
+            }
+
+            let mut networks_map = BTreeMap::new();
+            if confirm("Add networks?")? {
+                loop {
+                    let network = prompt("Network name")?;
+                    networks_map.insert(network, NetworkConfig {
+                        driver: "bridge".to_string(),
+                    });
+                    if !confirm("Add another network?")? {
+                        break;
+                    }
+                }
+            }
+
+            let default_output = FilenameConvention::default().compose_filename();
+            let output = prompt(&format!("Output filename (default: {})", default_output))?;
+            let mut output = if output.is_empty() { default_output.to_string() } else { output };
+
+            let mut fields = Vec::new();
+            for (i, service) in services.iter_mut().enumerate() {
+                let n = i + 1;
+                let env: Vec<String> = service.environment.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+                fields.extend([
+                    review_field(format!("Service {} name", n), service.name.clone(), |v| service.name = v.to_string()),
+                    review_field(format!("Service {} image", n), service.image.clone(), |v| service.image = v.to_string()),
+                    review_field(format!("Service {} ports", n), service.ports.join(","), |v| service.ports = parse_list(v)),
+                    review_field(format!("Service {} volumes", n), service.volumes.join(","), |v| service.volumes = parse_list(v)),
+                    review_field(format!("Service {} environment", n), env.join(","), |v| service.environment = parse_env_pairs(v)),
+                ]);
+            }
+            fields.push(review_field("Output filename", output.clone(), |v| output = v.to_string()));
+            review_spec(fields)?;
+
+            let spec = DockerComposeSpec {
+                services,
+                networks: networks_map,
+                ..Default::default()
+            };
+            // The wizard always runs the full compose lint
+            report_compose_lint(Path::new(&output), &spec, &[], out);
+            let rendered = render_template("docker-compose.yml", DOCKER_COMPOSE_TEMPLATE, &spec).expect("Failed to render docker-compose.yml");
+            write_to_file(Path::new(&output), &rendered, Some("docker-compose.yml"), out)?;
+        }
+        2 => {
+            // Dev Container
+            let name = prompt("Container name (default: Dev Container)")?;
+            let name = if name.is_empty() { "Dev Container".to_string() } else { name };
+            
+            let dockerfile = prompt("Dockerfile path (default: ./Dockerfile)")?;
+            let dockerfile = if dockerfile.is_empty() { "./Dockerfile".to_string() } else { dockerfile };
+            
+            let remote_user = prompt("Remote user (default: vscode)")?;
+            let remote_user = if remote_user.is_empty() { "vscode".to_string() } else { remote_user };
+            
+            let extensions = prompt("VSCode extensions (comma-separated)")?;
+            let extensions = if extensions.is_empty() {
+                vec!["ms-azuretools.vscode-docker".to_string()]
+            } else {
+                extensions.split(',').map(|s| s.trim().to_string()).collect()
+            };
+
+            let output = prompt("Output filename (default: devcontainer.json)")?;
+            let mut output = if output.is_empty() { "devcontainer.json".to_string() } else { output };
+
+            let mut spec = DevContainerSpec {
+                name,
+                source: DevContainerSource::Dockerfile { path: dockerfile.clone() },
+                remote_user,
+                customizations: DevContainerCustomizations {
+                    vscode_extensions: extensions,
+                    settings: default_devcontainer_settings(),
+                },
+                ..Default::default()
+            };
+            review_spec(vec![
+                review_field("Container name", spec.name.clone(), |v| spec.name = v.to_string()),
+                review_field("Dockerfile path", dockerfile, |v| {
+                    spec.source = DevContainerSource::Dockerfile { path: v.to_string() }
+                }),
+                review_field("Remote user", spec.remote_user.clone(), |v| spec.remote_user = v.to_string()),
+                review_field("VSCode extensions", spec.customizations.vscode_extensions.join(","), |v| {
+                    spec.customizations.vscode_extensions = parse_list(v)
+                }),
+                review_field("Output filename", output.clone(), |v| output = v.to_string()),
+            ])?;
+            let rendered = render_template("devcontainer.json", DEVCONTAINER_TEMPLATE, &spec).expect("Failed to render devcontainer.json");
+            write_to_file(Path::new(&output), &rendered, Some("devcontainer.json"), out)?;
+        }
+        3 => {
+            // Docker Bake
+            let mut targets = Vec::new();
+            loop {
+                section("Add Target");
+                let name = prompt("Target name")?;
+                let context = prompt("Context (default: .)")?;
+                let context = normalize_context(&context);
+                
+                let dockerfile = prompt("Dockerfile path (default: ./Dockerfile)")?;
+                let dockerfile = if dockerfile.is_empty() { "./Dockerfile".to_string() } else { dockerfile };
+                
+                let tag = prompt("Tag (default: latest)")?;
+                let tag = if tag.is_empty() { "latest".to_string() } else { tag };
+
+                targets.push(BakeTarget {
+                    name: name.clone(),
+                    context,
+                    dockerfile,
+                    tags: vec![format!("{}:{}", name, tag)],
+                    ..Default::default()
+                });
+
+                if !confirm("Add another target?")? {
+                    break;
+                }
+            }
+
+            let group = prompt("Group name (default: default)")?;
+            let mut group = if group.is_empty() { "default".to_string() } else { group };
+
+            let output = prompt("Output filename (default: docker-bake.hcl)")?;
+            let mut output = if output.is_empty() { "docker-bake.hcl".to_string() } else { output };
+
+            let mut fields = Vec::new();
+            for (i, target) in targets.iter_mut().enumerate() {
+                let n = i + 1;
+                fields.extend([
+                    review_field(format!("Target {} name", n), target.name.clone(), |v| target.name = v.to_string()),
+                    review_field(format!("Target {} context", n), target.context.clone(), |v| target.context = normalize_context(v)),
+                    review_field(format!("Target {} Dockerfile path", n), target.dockerfile.clone(), |v| target.dockerfile = v.to_string()),
+                    review_field(format!("Target {} tags", n), target.tags.join(","), |v| target.tags = parse_list(v)),
+                ]);
+            }
+            fields.push(review_field("Group name", group.clone(), |v| group = v.to_string()));
+            fields.push(review_field("Output filename", output.clone(), |v| output = v.to_string()));
+            review_spec(fields)?;
+
+            let spec = DockerBakeSpec {
+                group_name: group,
+                targets,
+                variables: Vec::new(),
+            };
+            let rendered = render_template("docker-bake.hcl", DOCKER_BAKE_TEMPLATE, &spec).expect("Failed to render docker-bake.hcl");
+            write_to_file(Path::new(&output), &rendered, Some("docker-bake.hcl"), out)?;
+        }
+        4 => {
+            // Complete Environment
+            let name = prompt("Project name")?;
+            
+            // Display names paired with the values `Init` matches on
+            let language_options = ["Python", "Node.js", "Rust", "Go", "Other"];
+            let language_values = ["python", "node", "rust", "go", "other"];
+            let language_idx = select_option(&language_options, "Select programming language:")?;
+            let language = language_values[language_idx].to_string();
+
+            let db_options = ["None", "PostgreSQL", "MySQL", "MongoDB"];
+            let db_values = ["", "postgres", "mysql", "mongodb"];
+            let db_idx = select_option(&db_options, "Select database:")?;
+            let database = if db_idx == 0 {
+                None
+            } else {
+                Some(db_values[db_idx].to_string())
+            };
+
+            let app_port = prompt("App port mapping (default: 8000:8000)")?;
+            let app_port = if app_port.is_empty() { "8000:8000".to_string() } else { port_mapping(&app_port) };
+
+            let db_port = match &database {
+                Some(db) => {
+                    let default_port = default_db_port(db);
+                    let db_port = prompt(&format!("Database port mapping (default: {})", default_port))?;
+                    (!db_port.is_empty()).then(|| port_mapping(&db_port))
+                }
+                None => None,
+            };
+
+            let service_options = ["None", "Redis", "Elasticsearch"];
+            let mut selected_services = Vec::new();
+            while confirm("Add additional service?")? {
+                let service_idx = select_option(&service_options, "Select service:")?;
+                let service = service_options[service_idx].to_lowercase();
+                if service_idx > 0 && !selected_services.contains(&service) {
+                    selected_services.push(service);
+                }
+            }
+            let services = if selected_services.is_empty() {
+                None
+            } else {
+                Some(selected_services.join(","))
+            };
+
+            // The app already depends on everything; let the other services depend on each other
+            let mut graph = DockerComposeSpec {
+                services: std::iter::once(&name)
+                    .chain(database.as_ref().map(|_| "db".to_string()).iter())
+                    .chain(selected_services.iter())
+                    .map(|service| ServiceSpec {
+                        name: service.clone(),
+                        ..Default::default()
+                    })
+                    .collect(),
+                ..Default::default()
+            };
+            // Seed the app's automatic dependencies so answers that loop back to it are caught
+            graph.services[0].depends_on = graph.services[1..].iter().map(|s| Dependency::new(&s.name)).collect();
+            if graph.services.len() > 2 {
+                for i in 1..graph.services.len() {
+                    loop {
+                        let answer = prompt(&format!(
+                            "Services that '{}' depends on (comma-separated, blank for none)",
+                            graph.services[i].name
+                        ))?;
+                        graph.services[i].depends_on = parse_list(&answer).iter().map(|dep| Dependency::new(dep)).collect();
+                        let findings = validate_compose_spec(&graph, &[]);
+                        if findings.is_empty() {
+                            break;
+                        }
+                        for finding in findings {
+                            println!("  {}", paint(ERROR, &finding));
+                        }
+                    }
+                }
+            }
+            let depends_on: Vec<String> = graph
+                .services
+                .iter()
+                .skip(1)
+                .filter(|s| !s.depends_on.is_empty())
+                .map(|s| {
+                    let deps: Vec<&str> = s.depends_on.iter().map(|d| d.service.as_str()).collect();
+                    format!("{}:{}", s.name, deps.join("|"))
+                })
+                .collect();
+            let depends_on = (!depends_on.is_empty()).then(|| depends_on.join(","));
+
+            let maintainer = prompt("Maintainer (default: Generated <generated@example.com>)")?;
+            let maintainer = if maintainer.is_empty() { "Generated <generated@example.com>".to_string() } else { maintainer };
+
+            let workdir = prompt("Working directory (default: /app)")?;
+            let workdir = if workdir.is_empty() { "/app".to_string() } else { workdir };
+
+            let output_dir = prompt("Output directory (default: .)")?;
+            let output_dir = if output_dir.is_empty() { ".".to_string() } else { output_dir };
+
+            // Call the existing init implementation
+            let mut command = Commands::Init {
+                name,
+                language,
+                database,
+                services_with_build: None,
+                extra_packages: None,
+                recommends: false,
+                no_recommends: false,
+                services,
+                output_dir,
+                wait_for: false,
+                filename_convention: FilenameConvention::default(),
+                app_port,
+                depends_on,
+                pin_images: false,
+                environment: None,
+                proxy: None,
+                port_offset: 0,
+                db_port,
+                maintainer,
+                workdir,
+                image: None,
+                readme: false,
+                vscode_tasks: false,
+                production: false,
+                no_healthchecks: false,
+                no_default_network: false,
+                dockerfile_name: "Dockerfile".to_string(),
+                single_stage: false,
+                entrypoint_script: false,
+                devcontainer_compose: false,
+                generate_passwords: false,
+                seed: None,
+                env_example: false,
+                dev_override: false,
+                watch: false,
+                bake: false,
+                git_init: false,
+                force: false,
+            };
+            if let Commands::Init { name, language, database, services, app_port, db_port, depends_on, maintainer, workdir, output_dir, .. } = &mut command {
+                let or_empty = |value: &Option<String>| value.clone().unwrap_or_default();
+                review_spec(vec![
+                    review_field("Project name", name.clone(), |v| *name = v.to_string()),
+                    review_field("Language (python, node, rust, go, other)", language.clone(), |v| *language = v.to_lowercase()),
+                    review_field("Database (postgres, mysql, mongodb or none)", or_empty(database), |v| *database = optional_answer(v)),
+                    review_field("Additional services (or none)", or_empty(services), |v| *services = optional_answer(v)),
+                    review_field("App port mapping", app_port.clone(), |v| *app_port = port_mapping(v)),
+                    review_field("Database port mapping (or none)", or_empty(db_port), |v| *db_port = optional_answer(v).map(|p| port_mapping(&p))),
+                    review_field("Dependencies (service:dep|dep, or none)", or_empty(depends_on), |v| *depends_on = optional_answer(v)),
+                    review_field("Maintainer", maintainer.clone(), |v| *maintainer = v.to_string()),
+                    review_field("Working directory", workdir.clone(), |v| *workdir = v.to_string()),
+                    review_field("Output directory", output_dir.clone(), |v| *output_dir = v.to_string()),
+                ])?;
+            }
+            command.execute(out)?;
+        }
+        _ => unreachable!(),
+    }
+
+    println!("\n{}", paint(SUCCESS, "Configuration files generated successfully!"));
+    Ok(())
+}
+
+// Add execute method to Commands enum
+impl Commands {
+    /// Directory the command writes into, where a relative `--manifest` is placed.
+    fn output_dir(&self) -> PathBuf {
+        let parent = |output: &str| Path::new(output).parent().filter(|p| !p.as_os_str().is_empty()).map(Path::to_path_buf);
+        match self {
+            Self::Init { output_dir, .. } | Self::Project { output_dir, .. } | Self::Clean { output_dir, .. } => PathBuf::from(output_dir),
+            Self::Dockerfile { output, .. } | Self::Bake { output, .. } | Self::Devcontainer { output, .. } | Self::Nomad { output, .. } => {
+                parent(output).unwrap_or_else(|| PathBuf::from("."))
+            }
+            Self::Compose { output, .. } => output.as_deref().and_then(parent).unwrap_or_else(|| PathBuf::from(".")),
+            Self::Templates { .. } | Self::Validate { .. } => PathBuf::from("."),
+        }
+    }
+
+    fn execute(self, out: OutputOptions) -> io::Result<()> {
+        match self {
+            Self::Init { name, language, database, services_with_build, extra_packages, recommends, no_recommends: _, services, output_dir, wait_for, filename_convention, app_port, db_port, depends_on, pin_images, environment, proxy, port_offset, maintainer, workdir, image, readme, vscode_tasks, production, no_healthchecks, no_default_network, dockerfile_name, single_stage, entrypoint_script, devcontainer_compose, generate_passwords, seed, env_example, dev_override, watch, bake, git_init, force } => {
+                if git_init && out.archive {
+                    return Err(invalid_input("--git-init needs the files on disk and can't be combined with --archive".to_string()));
+                }
+                // Create output directory if it doesn't exist
+                if out.writes_files() {
+                    std::fs::create_dir_all(&output_dir)?;
+                }
+                // Refuse a nested repository before anything is written
+                if git_init && !out.check && !out.dry_run && !force {
+                    let inside = git(&output_dir, ["rev-parse", "--is-inside-work-tree"])?;
+                    if inside.is_some_and(|o| o.status.success()) {
+                        return Err(invalid_input(format!(
+                            "{} is already inside a git repository; pass --force to run --git-init anyway",
+                            output_dir
+                        )));
+                    }
+                }
+                if dockerfile_name.is_empty() || dockerfile_name.contains(['/', '\\']) {
+                    return Err(invalid_input(format!(
+                        "--dockerfile-name must be a file name inside the output directory, got '{}'",
+                        dockerfile_name
+                    )));
+                }
+                // Every generated file that builds the app refers to the Dockerfile through this
+                let app_build = BuildConfig {
+                    dockerfile: (dockerfile_name != "Dockerfile").then(|| dockerfile_name.clone()),
+                    ..BuildConfig::new(".")
+                };
+                let mut files = Vec::new();
+                let app_port = shift_host_port(&port_mapping(&app_port), port_offset)?;
+
+                // 1. Generate Dockerfile based on language
+                let (base_image, packages) = match language.as_str() {
+                    "python" => ("python:3.12-slim", "python3-pip,python3-dev,build-essential"),
+                    "node" => ("node:22-slim", "npm"),
+                    "rust" => ("rust:1.83-slim", "cargo"),
+                    "go" => ("golang:1.23-bookworm", ""),
+                    _ => ("ubuntu:23.10", "curl,git"),
+                };
+
+                let mut packages: Vec<String> = parse_list(packages);
+                for package in parse_list(extra_packages.as_deref().unwrap_or_default()) {
+                    if !packages.contains(&package) {
+                        packages.push(package);
+                    }
+                }
+
+                // Compiled languages ship only the binary unless --single-stage
+                let runtime = if single_stage {
+                    None
+                } else {
+                    runtime_stage(&language, &name, &workdir, entrypoint_script || wait_for)
+                };
+                let dockerfile_spec = DockerfileSpec {
+                    base_image: base_image.to_string(),
+                    maintainer,
+                    packages,
+                    workdir: workdir.clone(),
+                    install_recommends: recommends,
+                    entrypoint: runtime.as_ref().map_or("/bin/bash", |r| &r.path).to_string(),
+                    entrypoint_script: entrypoint_script.then(|| "/entrypoint.sh".to_string()),
+                    source_steps: source_steps(&language),
+                    runtime,
+                    entrypoint_mode: *ENTRYPOINT_MODE.lock().unwrap(),
+                    ..Default::default()
+                };
+                let dockerfile = render_template("Dockerfile", DOCKERFILE_TEMPLATE, &dockerfile_spec)
+                    .expect("Failed to render Dockerfile");
+                let dockerfile_path = if dockerfile_name == "Dockerfile" {
+                    generated_path(&output_dir, "Dockerfile")
+                } else {
+                    Path::new(&output_dir).join(&dockerfile_name)
+                };
+                report_lint(&dockerfile_path, &dockerfile_spec, out);
+                write_to_file(&dockerfile_path, &dockerfile, Some("Dockerfile"), out)?;
+                files.push(dockerfile_path);
+
+                // Each built service gets a copy of the app's Dockerfile with its own command
+                let mut built_services = Vec::new();
+                for entry in parse_list(services_with_build.as_deref().unwrap_or_default()) {
+                    let (service, command) = entry.split_once('=').unwrap_or((&entry, ""));
+                    let service = service.trim();
+                    if service.is_empty() || !service.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')) {
+                        return Err(invalid_input(format!(
+                            "--services-with-build name '{}' may only use letters, digits, '-', '_' and '.'",
+                            service
+                        )));
+                    }
+                    let mut spec = dockerfile_spec.clone();
+                    let mut words = command.split_whitespace().map(str::to_string);
+                    if let Some(program) = words.next() {
+                        spec.entrypoint = program;
+                        spec.cmd = words.collect();
+                    }
+                    let file_name = format!("Dockerfile.{}", service);
+                    let rendered = render_template("Dockerfile", DOCKERFILE_TEMPLATE, &spec)
+                        .expect("Failed to render Dockerfile");
+                    let path = Path::new(&output_dir).join(&file_name);
+                    report_lint(&path, &spec, out);
+                    write_to_file(&path, &rendered, Some("Dockerfile"), out)?;
+                    files.push(path);
+                    built_services.push((service.to_string(), file_name));
+                }
+
+                if entrypoint_script {
+                    let script_spec = EntrypointScriptSpec {
+                        name: name.clone(),
+                        language: language.clone(),
+                    };
+                    let script = render_template("entrypoint.sh", ENTRYPOINT_SH_TEMPLATE, &script_spec)
+                        .expect("Failed to render entrypoint.sh");
+                    let script_path = generated_path(&output_dir, "entrypoint.sh");
+                    write_executable(&script_path, &script, Some("entrypoint.sh"), out)?;
+                    files.push(script_path);
+                }
+
+                // 2. Generate docker-compose.yml with services
+                let mut service_specs = Vec::new();
+                // Services don't name a network, so without this they simply share compose's default one
+                let mut networks_map = BTreeMap::new();
+                if !no_default_network {
+                    networks_map.insert("app_network".to_string(), NetworkConfig {
+                        driver: "bridge".to_string(),
+                    });
+                }
+
+                // Add main app service, built from the generated Dockerfile
+                service_specs.push(ServiceSpec {
+                    name: name.clone(),
+                    image: image.unwrap_or_default(),
+                    build: Some(app_build.clone()),
+                    ports: vec![port_mapping(&app_port)],
+                    depends_on: Vec::new(),
+                    environment: Vec::new(),
+                    volumes: vec![format!("./:{}", workdir)],
+                    ..Default::default()
+                });
+
+                // Add database if specified
+                if let Some(db) = &database {
+                    let (db_image, db_env) = match db.as_str() {
+                        "postgres" => ("postgres:latest", vec![
+                            ("POSTGRES_USER".to_string(), "admin".to_string()),
+                            ("POSTGRES_PASSWORD".to_string(), "password".to_string()),
+                        ]),
+                        "mysql" => ("mysql:latest", vec![
+                            ("MYSQL_ROOT_PASSWORD".to_string(), "password".to_string()),
+                            ("MYSQL_DATABASE".to_string(), "app".to_string()),
+                        ]),
+                        "mongodb" => ("mongo:latest", vec![
+                            ("MONGO_INITDB_ROOT_USERNAME".to_string(), "admin".to_string()),
+                            ("MONGO_INITDB_ROOT_PASSWORD".to_string(), "password".to_string()),
+                        ]),
+                        _ => ("postgres:latest", vec![
+                            ("POSTGRES_USER".to_string(), "admin".to_string()),
+                            ("POSTGRES_PASSWORD".to_string(), "password".to_string()),
+                        ]),
+                    };
+
+                    service_specs.push(ServiceSpec {
+                        name: "db".to_string(),
+                        image: db_image.to_string(),
+                        ports: vec![db_port.as_deref().map(port_mapping).unwrap_or_else(|| default_db_port(db).to_string())],
+                        depends_on: Vec::new(),
+                        environment: db_env,
+                        volumes: vec!["./data:/var/lib/postgresql/data".to_string()],
+                        ..Default::default()
+                    });
+
+                    // Update main app's depends_on
+                    service_specs[0].depends_on.push(Dependency::new("db"));
+                }
+
+                // Add additional services if specified
+                if let Some(additional_services) = &services {
+                    for service in additional_services.split(',') {
+                        let service = service.trim();
+                        match service {
+                            "redis" => {
+                                service_specs.push(ServiceSpec {
+                                    name: "redis".to_string(),
+                                    image: "redis:latest".to_string(),
+                                    ports: vec!["6379:6379".to_string()],
+                                    depends_on: Vec::new(),
+                                    environment: Vec::new(),
+                                    volumes: vec!["./redis-data:/data".to_string()],
+                                    ..Default::default()
+                                });
+                                service_specs[0].depends_on.push(Dependency::new("redis"));
+                            },
+                            "elasticsearch" => {
+                                service_specs.push(ServiceSpec {
+                                    name: "elasticsearch".to_string(),
+                                    image: "elasticsearch:8.7.0".to_string(),
+                                    ports: vec!["9200:9200".to_string()],
+                                    depends_on: Vec::new(),
+                                    environment: vec![
+                                        ("discovery.type".to_string(), "single-node".to_string()),
+                                        // 8.x turns on TLS and auth by default, which the plain-HTTP healthcheck can't get past
+                                        ("xpack.security.enabled".to_string(), "false".to_string()),
+                                        ("ES_JAVA_OPTS".to_string(), "-Xms512m -Xmx512m".to_string()),
+                                    ],
+                                    volumes: vec!["./es-data:/usr/share/elasticsearch/data".to_string()],
+                                    ..Default::default()
+                                });
+                                service_specs[0].depends_on.push(Dependency::new("elasticsearch"));
+                            },
+                            _ => (),
+                        }
+                    }
+                }
+
+                if let Some(proxy) = proxy {
+                    let port = container_port(&port_mapping(&app_port)).to_string();
+                    let (config_name, template, mount) = proxy.config();
+                    let config = render_template(config_name, template, &ProxySpec { app: name.clone(), port: port.clone() })
+                        .expect("Failed to render proxy config");
+                    let config_path = generated_path(&output_dir, config_name);
+                    write_to_file(&config_path, &config, Some(config_name), out)?;
+                    files.push(config_path);
+
+                    let mut volumes = vec![format!("./{}:{}:ro", config_name, mount)];
+                    if proxy == Proxy::Traefik {
+                        volumes.push("/var/run/docker.sock:/var/run/docker.sock:ro".to_string());
+                        service_specs[0].labels = vec![
+                            ("traefik.enable".to_string(), "true".to_string()),
+                            (format!("traefik.http.routers.{}.rule", name), format!("Host(`{}.localhost`)", name)),
+                            (format!("traefik.http.routers.{}.entrypoints", name), "web".to_string()),
+                            (format!("traefik.http.services.{}.loadbalancer.server.port", name), port),
+                        ];
+                    }
+                    // Only the proxy publishes ports; the app is reached through it
+                    service_specs[0].ports.clear();
+                    service_specs.push(ServiceSpec {
+                        name: "proxy".to_string(),
+                        image: proxy.image().to_string(),
+                        ports: vec!["80:80".to_string()],
+                        depends_on: vec![Dependency::new(&name)],
+                        volumes,
+                        ..Default::default()
+                    });
+                }
+
+                for (service, file_name) in built_services {
+                    if service_specs.iter().any(|s| s.name == service) {
+                        return Err(invalid_input(format!("--services-with-build '{}' clashes with a generated service", service)));
+                    }
+                    let worker = ServiceSpec {
+                        name: service,
+                        build: Some(BuildConfig {
+                            dockerfile: Some(file_name),
+                            ..app_build.clone()
+                        }),
+                        depends_on: service_specs[0].depends_on.clone(),
+                        // Like the app, the source mount stays out of a production-like base file
+                        volumes: if dev_override { Vec::new() } else { vec![format!("./:{}", workdir)] },
+                        ..Default::default()
+                    };
+                    service_specs.push(worker);
+                }
+
+                add_dependencies(&mut service_specs, depends_on.as_deref().unwrap_or_default())?;
+
+                if pin_images {
+                    for service in service_specs.iter_mut().skip(1) {
+                        if let Some(pinned) = pinned_image(&service.image) {
+                            service.image = pinned;
+                        }
+                    }
+                }
+
+                for service in service_specs.iter_mut().skip(1) {
+                    for port in &mut service.ports {
+                        *port = shift_host_port(port, port_offset)?;
+                    }
+                }
+
+                // Known images get their canonical healthcheck, and the app waits until they pass
+                if !no_healthchecks {
+                    for service in &mut service_specs {
+                        service.healthcheck = known_healthcheck(&service.image);
+                    }
+                    let healthy: HashSet<String> = service_specs
+                        .iter()
+                        .filter(|s| s.healthcheck.is_some())
+                        .map(|s| s.name.clone())
+                        .collect();
+                    for dep in service_specs[0].depends_on.iter_mut().filter(|d| healthy.contains(&d.service)) {
+                        dep.condition = Some("service_healthy".to_string());
+                    }
+                }
+                if production {
+                    for service in &mut service_specs {
+                        service.restart = Some("unless-stopped".to_string());
+                    }
+                }
+
+                // Wrap the app's start command so it blocks until its dependencies accept connections
+                if wait_for && !service_specs[0].depends_on.is_empty() {
+                    let targets: Vec<String> = service_specs[0]
+                        .depends_on
+                        .iter()
+                        .filter_map(|dep| {
+                            let service = service_specs.iter().find(|s| s.name == dep.service)?;
+                            let port = container_port(service.ports.first()?);
+                            Some(format!("{}:{}", dep.service, port))
+                        })
+                        .collect();
+
+                    let app = &mut service_specs[0];
+                    app.volumes.push(format!("./wait-for-it.sh:{}:ro", WAIT_FOR_IT_PATH));
+                    app.entrypoint = std::iter::once(WAIT_FOR_IT_PATH.to_string())
+                        .chain(targets)
+                        .chain(std::iter::once("--".to_string()))
+                        .collect();
+                    // The compose entrypoint replaces the image's, so hand off to it explicitly
+                    app.command = dockerfile_spec
+                        .entrypoint_script
+                        .iter()
+                        .chain(std::iter::once(&dockerfile_spec.entrypoint))
+                        .cloned()
+                        .collect();
+
+                    let script_path = generated_path(&output_dir, "wait-for-it.sh");
+                    write_executable(&script_path, WAIT_FOR_IT_SCRIPT, None, out)?;
+                    files.push(script_path);
+                }
+
+                if generate_passwords {
+                    for service in &mut service_specs {
+                        for (key, value) in &mut service.environment {
+                            if key.ends_with("PASSWORD") {
+                                *value = generate_password(seed.as_deref(), &format!("{}:{}", service.name, key))?;
+                            }
+                        }
+                    }
+                }
+
+                if env_example {
+                    let entries = extract_env_file(&mut service_specs);
+                    let example: Vec<(String, String)> = entries
+                        .iter()
+                        .map(|(key, value)| (key.clone(), if is_secret_key(key) { String::new() } else { value.clone() }))
+                        .collect();
+                    let env_path = generated_path(&output_dir, ".env");
+                    write_to_file(&env_path, &render_env_file("# Generated env file; keep it out of version control", &entries), None, out)?;
+                    files.push(env_path);
+                    let example_path = generated_path(&output_dir, ".env.example");
+                    write_to_file(&example_path, &render_env_file("# Generated env example; copy to .env and fill in the blanks", &example), None, out)?;
+                    files.push(example_path);
+                }
+
+                if let Some(environment) = environment {
+                    apply_environment(&mut service_specs, environment, &[]);
+                    if environment == Environment::Dev {
+                        if let Some(port) = debug_port(&language) {
+                            let port = shift_host_port(port, port_offset)?;
+                            service_specs[0].ports.push(port);
+                        }
+                    }
+                }
+
+                if watch {
+                    let app = &mut service_specs[0];
+                    let source_mount = format!("./:{}", workdir);
+                    app.volumes.retain(|v| *v != source_mount);
+                    app.watch = watch_rules(&language, &workdir);
+                }
+
+                // The base file keeps the built image; the override adds what only local dev wants
+                let dev_service = dev_override.then(|| {
+                    let app = &mut service_specs[0];
+                    let source_mount = format!("./:{}", workdir);
+                    app.volumes.retain(|v| *v != source_mount);
+                    let mut dev = ServiceSpec {
+                        name: app.name.clone(),
+                        volumes: vec![source_mount],
+                        ..Default::default()
+                    };
+                    // Reloading needs the toolchain, which only the builder stage has
+                    let script = match dockerfile_spec.runtime {
+                        Some(_) => {
+                            dev.build = Some(BuildConfig {
+                                target: Some("builder".to_string()),
+                                ..app_build.clone()
+                            });
+                            None
+                        }
+                        None => dockerfile_spec.entrypoint_script.clone(),
+                    };
+                    if let Some(reload) = reload_command(&language, container_port(&port_mapping(&app_port))) {
+                        if !app.entrypoint.is_empty() {
+                            // wait-for-it owns the entrypoint, so only swap the command it execs
+                            dev.command = script.into_iter().chain(reload).collect();
+                        } else if script.is_some() {
+                            dev.command = reload;
+                        } else {
+                            dev.entrypoint = reload;
+                        }
+                    }
+                    dev
+                });
+
+                // Every service compose builds from source gets a bake target with the same tag
+                let bake_spec = bake.then(|| DockerBakeSpec {
+                    group_name: "default".to_string(),
+                    targets: service_specs
+                        .iter()
+                        .filter_map(|service| {
+                            let build = service.build.as_ref()?;
+                            let tag = if service.image.is_empty() { format!("{}:latest", service.name) } else { service.image.clone() };
+                            Some(BakeTarget {
+                                name: service.name.clone(),
+                                context: build.context.clone(),
+                                dockerfile: build.dockerfile.clone().unwrap_or_else(|| "Dockerfile".to_string()),
+                                tags: vec![tag],
+                                ..Default::default()
+                            })
+                        })
+                        .collect(),
+                    variables: Vec::new(),
+                });
+                if let Some(bake_spec) = &bake_spec {
+                    for target in &bake_spec.targets {
+                        validate_hcl_identifier(&target.name, "target")?;
+                    }
+                }
+
+                let extra_services: Vec<String> = service_specs
+                    .iter()
+                    .skip(1)
+                    .filter(|s| s.name != "db")
+                    .map(|s| s.name.clone())
+                    .collect();
+                let compose_spec = DockerComposeSpec {
+                    project_name: Some(sanitize_project_name(&name)).filter(|n| !n.is_empty()),
+                    services: service_specs,
+                    networks: networks_map,
+                    ..Default::default()
+                };
+                let compose = render_template("docker-compose.yml", DOCKER_COMPOSE_TEMPLATE, &compose_spec)
+                    .expect("Failed to render docker-compose.yml");
+                let compose_path = generated_path(&output_dir, filename_convention.compose_filename());
+                report_compose_lint(&compose_path, &compose_spec, &[], out);
+                let base_services: Vec<String> = compose_spec.services.iter().map(|s| s.name.clone()).collect();
+                write_to_file(&compose_path, &compose, Some("docker-compose.yml"), out)?;
+                files.push(compose_path);
+
+                if let Some(bake_spec) = bake_spec {
+                    let rendered = render_template("docker-bake.hcl", DOCKER_BAKE_TEMPLATE, &bake_spec)
+                        .expect("Failed to render docker-bake.hcl");
+                    let bake_path = generated_path(&output_dir, "docker-bake.hcl");
+                    write_to_file(&bake_path, &rendered, Some("docker-bake.hcl"), out)?;
+                    files.push(bake_path);
+                }
+
+                if let Some(dev_service) = dev_service {
+                    let override_spec = DockerComposeSpec {
+                        services: vec![dev_service],
+                        ..Default::default()
+                    };
+                    let rendered = render_template("docker-compose.yml", DOCKER_COMPOSE_TEMPLATE, &override_spec)
+                        .expect("Failed to render compose override");
+                    let override_path = generated_path(&output_dir, filename_convention.override_filename());
+                    report_compose_lint(&override_path, &override_spec, &base_services, out);
+                    write_to_file(&override_path, &rendered, Some("docker-compose.yml"), out)?;
+                    files.push(override_path);
+                }
+
+                // 3. Generate devcontainer.json
+                let source = if devcontainer_compose {
+                    // The app's image entrypoint exits at once; keep the container up for the editor
+                    let keep_alive = DockerComposeSpec {
+                        services: vec![ServiceSpec {
+                            name: name.clone(),
+                            entrypoint: vec!["sleep".to_string(), "infinity".to_string()],
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    };
+                    let rendered = render_template("docker-compose.yml", DOCKER_COMPOSE_TEMPLATE, &keep_alive)
+                        .expect("Failed to render devcontainer compose file");
+                    let keep_alive_path = generated_path(&output_dir, DEVCONTAINER_COMPOSE_FILE);
+                    if out.writes_files() {
+                        std::fs::create_dir_all(keep_alive_path.parent().unwrap_or(Path::new(&output_dir)))?;
+                    }
+                    report_compose_lint(&keep_alive_path, &keep_alive, &base_services, out);
+                    write_to_file(&keep_alive_path, &rendered, Some("docker-compose.yml"), out)?;
+                    files.push(keep_alive_path);
+
+                    let mut compose_files = vec![filename_convention.compose_filename().to_string()];
+                    if dev_override {
+                        compose_files.push(filename_convention.override_filename().to_string());
+                    }
+                    compose_files.push(DEVCONTAINER_COMPOSE_FILE.to_string());
+                    Some(DevContainerSource::Compose {
+                        files: compose_files,
+                        service: name.clone(),
+                        workspace_folder: workdir.clone(),
+                    })
+                } else {
+                    None
+                };
+                let devcontainer_spec = DevContainerSpec {
+                    name: format!("{} Dev Container", name),
+                    source: if let Some(source) = source {
+                        source
+                    } else {
+                        DevContainerSource::Dockerfile {
+                            path: format!("./{}", dockerfile_name),
+                        }
+                    },
+                    remote_user: "vscode".to_string(),
+                    customizations: DevContainerCustomizations {
+                        vscode_extensions: match language.as_str() {
+                            "python" => vec![
+                                "ms-python.python".to_string(),
+                                "ms-python.vscode-pylance".to_string(),
+                            ],
+                            "node" => vec![
+                                "dbaeumer.vscode-eslint".to_string(),
+                                "esbenp.prettier-vscode".to_string(),
+                            ],
+                            "rust" => vec![
+                                "rust-lang.rust-analyzer".to_string(),
+                                "serayuzgur.crates".to_string(),
+                            ],
+                            "go" => vec!["golang.go".to_string()],
+                            _ => vec![],
+                        },
+                        settings: default_devcontainer_settings(),
+                    },
+                    ..Default::default()
+                };
+                let devcontainer = render_template("devcontainer.json", DEVCONTAINER_TEMPLATE, &devcontainer_spec)
+                    .expect("Failed to render devcontainer.json");
+                let devcontainer_path = generated_path(&output_dir, "devcontainer.json");
+                write_to_file(&devcontainer_path, &devcontainer, Some("devcontainer.json"), out)?;
+                files.push(devcontainer_path);
+
+                if readme {
+                    let readme_spec = ReadmeSpec {
+                        name: name.clone(),
+                        language: language.clone(),
+                        database: database.clone(),
+                        services: extra_services.clone(),
+                        app_port: port_mapping(&app_port),
+                        proxy: proxy.and_then(|p| p.to_possible_value()).map(|v| v.get_name().to_string()),
+                        proxy_port: 80 + u32::from(port_offset),
+                    };
+                    let rendered = render_template("README.md", README_TEMPLATE, &readme_spec)
+                        .expect("Failed to render README.md");
+                    // Never clobber a hand-written README, whatever the overwrite policy
+                    let readme_path = generated_path(&output_dir, "README.md");
+                    write_to_file(&readme_path, &rendered, Some("README.md"), OutputOptions { policy: OverwritePolicy::Skip, ..out })?;
+                    files.push(readme_path);
+                }
+
+                if vscode_tasks {
+                    let mut compose_files = vec![filename_convention.compose_filename().to_string()];
+                    if dev_override {
+                        compose_files.push(filename_convention.override_filename().to_string());
+                    }
+                    let task = |label: &str, args: &[&str]| (label.to_string(), args.iter().map(|a| a.to_string()).collect());
+                    let tasks_spec = VscodeTasksSpec {
+                        compose_files,
+                        tasks: vec![task("build", &["build"]), task("up", &["up", "-d"]), task("down", &["down"])],
+                    };
+                    let rendered = render_template("tasks.json", VSCODE_TASKS_TEMPLATE, &tasks_spec)
+                        .expect("Failed to render tasks.json");
+                    let tasks_path = generated_path(&output_dir, VSCODE_TASKS_FILE);
+                    if out.writes_files() {
+                        std::fs::create_dir_all(tasks_path.parent().unwrap_or(Path::new(&output_dir)))?;
+                    }
+                    // Hand-written tasks are more valuable than ours, whatever the overwrite policy
+                    write_to_file(&tasks_path, &rendered, Some("tasks.json"), OutputOptions { policy: OverwritePolicy::Skip, ..out })?;
+                    files.push(tasks_path);
+                }
+
+                // Write everything before git commits it and the summary lists it
+                flush_writes()?;
+                if git_init && !out.check && !out.dry_run {
+                    // .env holds real values; only its example belongs in the first commit
+                    let committed: Vec<PathBuf> = files.iter().filter(|f| !f.ends_with(".env")).cloned().collect();
+                    init_git_repo(&output_dir, &committed)?;
+                }
+
+                if !out.check && !out.dry_run {
+                    let or_none = |items: &[String]| if items.is_empty() { "none".to_string() } else { items.join(", ") };
+                    println!("\nGenerated development environment in: {}", output_dir);
+                    println!("  Language: {} ({})", language, base_image);
+                    println!("  Database: {}", database.as_deref().unwrap_or("none"));
+                    println!("  Services: {}", or_none(&extra_services));
+                    println!("  Files:");
+                    for file in &files {
+                        println!("    {}", file.display());
+                    }
+                    println!("\nNext steps:");
+                    if output_dir != "." {
+                        println!("  cd {}", output_dir);
+                    }
+                    println!("  docker compose up --build");
+                }
+                Ok(())
+            }
+            Self::Compose {
+                output,
+                filename_convention,
+                format,
+                services,
+                service_template,
+                count,
+                template_port,
+                ports,
+                volumes,
+                env,
+                expand_env,
+                networks,
+                depends_on,
+                override_output,
+                dev_only,
+                environment,
+                dev_services,
+                dev_build,
+                build_arg,
+                build_target,
+                cpus,
+                memory,
+                cpu_shares,
+                cpu_reservation,
+                mem_reservation,
+                limits_style,
+                restart_policy,
+                depends_on_style,
+                sort_services,
+                project_name,
+                includes,
+                annotate,
+                config_inline,
+                split_by,
+                service_group,
+                share_env,
+                x_extensions,
+                read_only,
+                security_opt,
+                cap_add,
+                cap_drop,
+                init,
+                interactive,
+                service_user,
+                working_dir,
+                hostname,
+                network_mode,
+                shm_size,
+                watch,
+                pull_policy,
+            } => {
+                let templated = match (&service_template, count) {
+                    (Some(template), Some(count)) => expand_service_template(template, count, template_port.as_deref())?,
+                    _ => Vec::new(),
+                };
+                let mut names = match (&services, templated.is_empty()) {
+                    (Some(services), _) => parse_list(services),
+                    (None, true) => vec!["app".to_string()],
+                    (None, false) => Vec::new(),
+                };
+                names.extend(templated.iter().map(|service| service.name.clone()));
+                let mut ports = assign_per_service(ports.as_deref(), &names, "--ports")?;
+                let mut volumes = assign_per_service(volumes.as_deref(), &names, "--volumes")?;
+                let mut service_specs: Vec<ServiceSpec> = names
+                    .iter()
+                    .map(|name| ServiceSpec {
+                        name: name.clone(),
+                        image: format!("{}:latest", name),
+                        ports: ports.remove(name).unwrap_or_default(),
+                        volumes: volumes.remove(name).unwrap_or_default(),
+                        ..Default::default()
+                    })
+                    .collect();
+                for (service, templated) in service_specs.iter_mut().rev().zip(templated.into_iter().rev()) {
+                    service.image = templated.image;
+                    service.ports.extend(templated.ports);
+                }
+
+                // KEY=VALUE applies to every service, service:KEY=VALUE to just that one
+                for entry in env.as_deref().map(parse_list).unwrap_or_default() {
+                    let (target, pair) = match entry.split_once(':') {
+                        Some((service, pair)) if names.iter().any(|n| n == service) => (Some(service), pair),
+                        _ => (None, entry.as_str()),
+                    };
+                    let Some((key, value)) = pair.split_once('=') else {
+                        return Err(invalid_input(format!("--env expects KEY=VALUE, got '{}'", entry)));
+                    };
+                    let value = if expand_env { expand_host_env(value.trim())? } else { value.trim().to_string() };
+                    for service in service_specs.iter_mut().filter(|s| target.is_none_or(|t| s.name == t)) {
+                        service.environment.push((key.trim().to_string(), value.clone()));
+                    }
+                }
+
+                add_dependencies(&mut service_specs, depends_on.as_deref().unwrap_or_default())?;
+                if depends_on_style == Some(DependsOnStyle::Short) {
+                    for service in &service_specs {
+                        for dep in service.depends_on.iter().filter(|d| d.condition.is_some()) {
+                            warning!("--depends-on-style short drops the condition/restart on {} -> {}", service.name, dep.service);
+                        }
+                    }
+                }
+
+                let mut limits = parse_resource_limits(
+                    cpus.as_deref(),
+                    memory.as_deref(),
+                    cpu_shares.as_deref(),
+                    cpu_reservation.as_deref(),
+                    mem_reservation.as_deref(),
+                    &names,
+                )?;
+                for service in &mut service_specs {
+                    service.resources = limits.remove(&service.name);
+                }
+                for (name, value) in parse_service_values(restart_policy.as_deref().unwrap_or_default(), &names, "--restart-policy")? {
+                    let policy = parse_restart_policy(&name, &value)?;
+                    if let Some(service) = service_specs.iter_mut().find(|s| s.name == name) {
+                        service.restart_policy = Some(policy);
+                    }
+                }
+
+                for name in parse_service_names(init.as_deref().unwrap_or_default(), &names, "--init")? {
+                    if let Some(service) = service_specs.iter_mut().find(|s| s.name == name) {
+                        service.init = true;
+                    }
+                }
+                for name in parse_service_names(interactive.as_deref().unwrap_or_default(), &names, "--interactive")? {
+                    if let Some(service) = service_specs.iter_mut().find(|s| s.name == name) {
+                        service.stdin_open = true;
+                        service.tty = true;
+                    }
+                }
+                for name in parse_service_names(read_only.as_deref().unwrap_or_default(), &names, "--read-only")? {
+                    if let Some(service) = service_specs.iter_mut().find(|s| s.name == name) {
+                        service.read_only = true;
+                    }
+                }
+                for (name, opt) in parse_service_values(security_opt.as_deref().unwrap_or_default(), &names, "--security-opt")? {
+                    if let Some(service) = service_specs.iter_mut().find(|s| s.name == name) {
+                        service.security_opt.push(opt);
+                    }
+                }
+                for (name, user) in parse_service_values(service_user.as_deref().unwrap_or_default(), &names, "--service-user")? {
+                    if !is_valid_user(&user) {
+                        return Err(invalid_input(format!(
+                            "--service-user for '{}' must be uid[:gid] (numeric or a user/group name), got '{}'",
+                            name, user
+                        )));
+                    }
+                    if let Some(service) = service_specs.iter_mut().find(|s| s.name == name) {
+                        service.user = Some(user);
+                    }
+                }
+                for (name, size) in parse_service_values(shm_size.as_deref().unwrap_or_default(), &names, "--shm-size")? {
+                    if !validate_byte_size(&size) {
+                        return Err(invalid_input(format!(
+                            "--shm-size for '{}' must be a size like 256m or 1gb, got '{}'",
+                            name, size
+                        )));
+                    }
+                    if let Some(service) = service_specs.iter_mut().find(|s| s.name == name) {
+                        service.shm_size = Some(size);
+                    }
+                }
+                for (name, policy) in parse_service_values(pull_policy.as_deref().unwrap_or_default(), &names, "--pull-policy")? {
+                    if !PULL_POLICIES.contains(&policy.as_str()) {
+                        return Err(invalid_input(format!(
+                            "--pull-policy for '{}' must be one of {}, got '{}'",
+                            name,
+                            PULL_POLICIES.join(", "),
+                            policy
+                        )));
+                    }
+                    if let Some(service) = service_specs.iter_mut().find(|s| s.name == name) {
+                        service.pull_policy = Some(policy);
+                    }
+                }
+                for (name, rule) in parse_service_values(watch.as_deref().unwrap_or_default(), &names, "--watch")? {
+                    let rule = parse_watch_rule(&name, &rule)?;
+                    if let Some(service) = service_specs.iter_mut().find(|s| s.name == name) {
+                        service.watch.push(rule);
+                    }
+                }
+                for (name, dir) in parse_service_values(working_dir.as_deref().unwrap_or_default(), &names, "--working-dir")? {
+                    if let Some(service) = service_specs.iter_mut().find(|s| s.name == name) {
+                        service.working_dir = Some(dir);
+                    }
+                }
+                for (name, host) in parse_service_values(hostname.as_deref().unwrap_or_default(), &names, "--hostname")? {
+                    if let Some(service) = service_specs.iter_mut().find(|s| s.name == name) {
+                        service.hostname = Some(host);
+                    }
+                }
+                for (name, mode) in parse_service_values(network_mode.as_deref().unwrap_or_default(), &names, "--network-mode")? {
+                    if let Some(service) = service_specs.iter_mut().find(|s| s.name == name) {
+                        service.network_mode = Some(mode);
+                    }
+                }
+                let known: HashSet<&str> = names.iter().map(String::as_str).collect();
+                if let Some(problem) = service_specs.iter().find_map(|s| network_mode_problem(s, &known)) {
+                    return Err(invalid_input(format!("--network-mode: {}", problem)));
+                }
+                for (flag, caps, add) in [("--cap-add", &cap_add, true), ("--cap-drop", &cap_drop, false)] {
+                    for (name, cap) in parse_service_values(caps.as_deref().unwrap_or_default(), &names, flag)? {
+                        if !is_known_capability(&cap) {
+                            warning!("{} for '{}': unknown capability '{}'", flag, name, cap);
+                        }
+                        if let Some(service) = service_specs.iter_mut().find(|s| s.name == name) {
+                            let list = if add { &mut service.cap_add } else { &mut service.cap_drop };
+                            list.push(cap);
+                        }
+                    }
+                }
+
+                for (name, arg) in parse_service_values(build_arg.as_deref().unwrap_or_default(), &names, "--build-arg")? {
+                    let Some((key, value)) = arg.split_once('=') else {
+                        return Err(invalid_input(format!("--build-arg expects service:KEY=VALUE, got '{}:{}'", name, arg)));
+                    };
+                    if let Some(service) = service_specs.iter_mut().find(|s| s.name == name) {
+                        let build = service.build.get_or_insert_with(|| BuildConfig::new("."));
+                        build.args.push((key.trim().to_string(), value.trim().to_string()));
+                    }
+                }
+                for (name, target) in parse_service_values(build_target.as_deref().unwrap_or_default(), &names, "--build-target")? {
+                    if let Some(service) = service_specs.iter_mut().find(|s| s.name == name) {
+                        service.build.get_or_insert_with(|| BuildConfig::new(".")).target = Some(target);
+                    }
+                }
+
+                let sharing = parse_service_names(share_env.as_deref().unwrap_or_default(), &names, "--share-env")?;
+                let common_env = share_environment(&mut service_specs, &sharing);
+                if !sharing.is_empty() && common_env.is_empty() {
+                    warning!("--share-env: {} have no environment entries in common", sharing.join(", "));
+                }
+
+                let mut top_level_extensions = BTreeMap::new();
+                for (service, key, value) in parse_x_extensions(&x_extensions, &names)? {
+                    match service {
+                        Some(name) => {
+                            if let Some(service) = service_specs.iter_mut().find(|s| s.name == name) {
+                                service.x_extensions.insert(key, value);
+                            }
+                        }
+                        None => {
+                            top_level_extensions.insert(key, value);
+                        }
+                    }
+                }
+
+                let dev_services = parse_service_names(dev_services.as_deref().unwrap_or_default(), &names, "--dev-services")?;
+                match environment {
+                    Some(environment) => apply_environment(&mut service_specs, environment, &dev_services),
+                    None if !dev_services.is_empty() => warning!("--dev-services only applies with --environment prod"),
+                    None => (),
+                }
+
+                let networks_map = parse_list(&networks)
+                    .into_iter()
+                    .map(|name| (name, NetworkConfig { driver: "bridge".to_string() }))
+                    .collect();
+
+                let override_output = override_output
+                    .map(|o| o.unwrap_or_else(|| format.filename(filename_convention.override_filename())));
+                let override_services = match &override_output {
+                    Some(_) => {
+                        let dev_build = dev_build.as_deref().map(parse_list).unwrap_or_default();
+                        split_dev_overrides(&mut service_specs, &dev_only, &dev_build)
+                    }
+                    None => Vec::new(),
+                };
+
+                // --build-arg, --build-target and --dev-build may all attach a build, so check only now;
+                // a build moved into the override still counts, since compose merges the two files
+                for service in &service_specs {
+                    let builds = service.build.is_some() || override_services.iter().any(|o| o.name == service.name && o.build.is_some());
+                    if builds {
+                        continue;
+                    }
+                    if service.pull_policy.as_deref() == Some("build") {
+                        warning!("--pull-policy: '{}' has no build, so pull_policy: build has nothing to build", service.name);
+                    }
+                    if !service.watch.is_empty() {
+                        warning!("--watch: '{}' has no build, and compose watch only acts on services it builds", service.name);
+                    }
+                }
+
+                let output = output.unwrap_or_else(|| format.filename(filename_convention.compose_filename()));
+                if sort_services {
+                    let order = dependency_order(&service_specs).map_err(|cycle| {
+                        invalid_input(format!("--sort-services: depends_on cycle between {}", quoted_list(&cycle)))
+                    })?;
+                    let mut slots: Vec<Option<ServiceSpec>> = service_specs.into_iter().map(Some).collect();
+                    service_specs = order.into_iter().filter_map(|i| slots[i].take()).collect();
+                }
+                let spec = DockerComposeSpec {
+                    project_name: project_name.as_deref().map(validate_project_name).transpose()?,
+                    includes: includes
+                        .into_iter()
+                        .map(|path| {
+                            let path = path.trim().to_string();
+                            if path.is_empty() {
+                                return Err(invalid_input("--include needs a compose file path".to_string()));
+                            }
+                            Ok(path)
+                        })
+                        .collect::<io::Result<_>>()?,
+                    services: service_specs,
+                    networks: networks_map,
+                    limits_style,
+                    depends_on_style,
+                    x_extensions: top_level_extensions,
+                    common_env,
+                    annotate,
+                    configs: parse_inline_configs(&config_inline)?,
+                };
+                let base_services: Vec<String> = spec.services.iter().map(|s| s.name.clone()).collect();
+
+                let files = match split_by {
+                    Some(SplitBy::Group) => {
+                        let groups = parse_service_values(service_group.as_deref().unwrap_or_default(), &names, "--service-group")?;
+                        if let Some((_, group)) = groups.iter().find(|(_, g)| !g.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')) {
+                            return Err(invalid_input(format!("--service-group name '{}' may only use letters, digits, '-' and '_'", group)));
+                        }
+                        split_compose_by_group(spec, &groups.into_iter().collect(), &output)
+                    }
+                    None => vec![(output, spec)],
+                };
+                let mut written = Vec::new();
+                for (path, spec) in files {
+                    // Split files may refer to services in the others, which compose merges in
+                    let others: Vec<String> =
+                        base_services.iter().filter(|name| !spec.services.iter().any(|s| s.name == **name)).cloned().collect();
+                    report_compose_lint(Path::new(&path), &spec, &others, out);
+                    write_to_file(Path::new(&path), &format.render(&spec)?, Some("docker-compose.yml"), out)?;
+                    written.push(path);
+                }
+
+                if let Some(override_output) = &override_output {
+                    let override_spec = DockerComposeSpec {
+                        services: override_services,
+                        ..Default::default()
+                    };
+                    report_compose_lint(Path::new(override_output), &override_spec, &base_services, out);
+                    write_to_file(Path::new(override_output), &format.render(&override_spec)?, Some("docker-compose.yml"), out)?;
+                    written.push(override_output.clone());
+                }
+                flush_writes()?;
+                if written.len() > 1 {
+                    let flags: Vec<String> = written.iter().map(|f| format!("-f {}", f)).collect();
+                    let stack = if override_output.is_some() { "dev stack" } else { "stack" };
+                    println!("Start the {} with: docker compose {} up", stack, flags.join(" "));
+                }
+                Ok(())
+            }
+            Self::Bake { output, group, targets, contexts, dockerfiles, dockerfile_inline, tags, no_cache, pull, output_types, secrets, annotations, variables, labels_from_git, platforms, amd64, arm64, compact } => {
+                let platforms = match platforms {
+                    Some(platforms) => parse_list(&platforms),
+                    None => shortcut_platforms(amd64, arm64),
+                };
+                let names = targets.as_deref().map(parse_list).unwrap_or_else(|| vec!["app".to_string()]);
+                let contexts = contexts.as_deref().map(parse_list).unwrap_or_default();
+                let dockerfiles = dockerfiles.as_deref().map(parse_list).unwrap_or_default();
+                validate_hcl_identifier(&group, "group")?;
+                for name in &names {
+                    validate_hcl_identifier(name, "target")?;
+                }
+                for (flag, values) in [("--contexts", &contexts), ("--dockerfiles", &dockerfiles)] {
+                    if values.len() > names.len() {
+                        warning!("{} has {} entries for {} target(s); the extra ones are ignored", flag, values.len(), names.len());
+                    }
+                }
+                let mut tags = match tags.as_deref() {
+                    Some(tags) => parse_bake_tags(tags, &names)?,
+                    None => HashMap::new(),
+                };
+                let no_cache = selected_targets(no_cache, &names, "--no-cache")?;
+                let pull = selected_targets(pull, &names, "--pull")?;
+                let mut outputs = parse_bake_entries(&output_types, &names, "--output-type", validate_bake_output)?;
+                let mut secrets = parse_bake_entries(&secrets, &names, "--secret", validate_bake_secret)?;
+                let mut annotations = parse_bake_annotations(&annotations, &names)?;
+                let variables = variables
+                    .iter()
+                    .map(|var| match var.split_once('=') {
+                        Some((name, default)) if !name.trim().is_empty() => {
+                            Ok((name.trim().to_string(), default.trim().to_string()))
+                        }
+                        _ => Err(invalid_input(format!("--var expects NAME=DEFAULT, got '{}'", var))),
+                    })
+                    .collect::<io::Result<Vec<_>>>()?;
+                for (name, _) in &variables {
+                    validate_hcl_identifier(name, "variable")?;
+                }
+                let labels = if labels_from_git { git_source_labels()? } else { Vec::new() };
+                let mut inline_dockerfiles = HashMap::new();
+                for entry in &dockerfile_inline {
+                    let Some((target, value)) = entry.split_once(':') else {
+                        return Err(invalid_input(format!("--dockerfile-inline expects TARGET:FILE_OR_TEXT, got '{}'", entry)));
+                    };
+                    let Some(i) = names.iter().position(|name| name == target.trim()) else {
+                        return Err(invalid_input(format!("--dockerfile-inline: unknown target '{}'", target)));
+                    };
+                    if dockerfiles.get(i).is_some() {
+                        return Err(invalid_input(format!(
+                            "target '{}' has both a --dockerfiles path and --dockerfile-inline; pick one",
+                            names[i]
+                        )));
+                    }
+                    let content = if Path::new(value).is_file() {
+                        let bytes = std::fs::read(value)?;
+                        String::from_utf8(bytes)
+                            .map_err(|_| invalid_input(format!("--dockerfile-inline {}: {} is not valid UTF-8", names[i], value)))?
+                    } else {
+                        value.to_string()
+                    };
+                    if content.trim().is_empty() {
+                        return Err(invalid_input(format!("--dockerfile-inline for '{}' is empty", names[i])));
+                    }
+                    if inline_dockerfiles.insert(names[i].clone(), HclHeredoc::new(&content)).is_some() {
+                        return Err(invalid_input(format!("--dockerfile-inline is given more than once for '{}'", names[i])));
+                    }
+                }
+
+                let targets = names
+                    .iter()
+                    .enumerate()
+                    .map(|(i, name)| BakeTarget {
+                        name: name.clone(),
+                        context: normalize_context(contexts.get(i).map_or(".", String::as_str)),
+                        dockerfile: dockerfiles.get(i).cloned().unwrap_or_else(|| "./Dockerfile".to_string()),
+                        tags: tags.remove(name).unwrap_or_else(|| vec![format!("{}:latest", name)]),
+                        no_cache: no_cache.contains(name),
+                        pull: pull.contains(name),
+                        outputs: outputs.remove(name).unwrap_or_default(),
+                        secrets: secrets.remove(name).unwrap_or_default(),
+                        annotations: annotations.remove(name).unwrap_or_default(),
+                        labels: labels.clone(),
+                        platforms: platforms.clone(),
+                        dockerfile_inline: inline_dockerfiles.remove(name),
+                    })
+                    .collect();
+
+                let spec = DockerBakeSpec {
+                    group_name: group,
+                    targets,
+                    variables,
+                };
+                let mut rendered = render_template("docker-bake.hcl", DOCKER_BAKE_TEMPLATE, &spec)
+                    .expect("Failed to render docker-bake.hcl");
+                if compact {
+                    rendered = compact_hcl(&rendered);
+                }
+                write_to_file(Path::new(&output), &rendered, Some("docker-bake.hcl"), out)
+            }
+            Self::Devcontainer { name, dockerfile, compose_file, service, workspace_folder, remote_user, extensions, output, settings, mounts, run_args, container_env, compact } => {
+                for mount in &mounts {
+                    validate_devcontainer_mount(mount)?;
+                }
+                let container_env = container_env
+                    .iter()
+                    .map(|entry| match entry.split_once('=') {
+                        Some((key, value)) => Ok((key.trim().to_string(), value.to_string())),
+                        None => Err(invalid_input(format!("--container-env expects KEY=VALUE, got '{}'", entry))),
+                    })
+                    .collect::<io::Result<Vec<_>>>()?;
+                let spec = DevContainerSpec {
+                    name,
+                    source: match (compose_file, service) {
+                        (Some(file), Some(service)) => DevContainerSource::Compose {
+                            files: vec![file],
+                            service,
+                            workspace_folder,
+                        },
+                        _ => DevContainerSource::Dockerfile { path: dockerfile },
+                    },
+                    remote_user,
+                    mounts,
+                    run_args: run_args.iter().flat_map(|a| a.split_whitespace()).map(String::from).collect(),
+                    container_env,
+                    customizations: DevContainerCustomizations {
+                        vscode_extensions: parse_list(&extensions),
+                        settings: devcontainer_settings(&settings)?,
+                    },
+                };
+                let mut rendered = render_template("devcontainer.json", DEVCONTAINER_TEMPLATE, &spec)
+                    .expect("Failed to render devcontainer.json");
+                if compact {
+                    rendered = minify_json(&rendered);
+                }
+                write_to_file(Path::new(&output), &rendered, Some("devcontainer.json"), out)
+            }
+            Self::Project { from_file, output_dir } => {
+                let contents = std::fs::read_to_string(&from_file)?;
+                let project: ProjectSpec = serde_yaml::from_str(&contents)
+                    .map_err(|e| invalid_input(format!("{}: {}", from_file, e)))?;
+                if project.dockerfile.is_none() && project.compose.is_none() && project.devcontainer.is_none() && project.bake.is_none() {
+                    return Err(invalid_input(format!(
+                        "{} has none of the dockerfile, compose, devcontainer or bake sections",
+                        from_file
+                    )));
+                }
+                if out.writes_files() {
+                    std::fs::create_dir_all(&output_dir)?;
+                }
+
+                if let Some(mut spec) = project.dockerfile {
+                    if spec.base_image.is_empty() {
+                        return Err(invalid_input(format!("{}: dockerfile.base_image is required", from_file)));
+                    }
+                    if spec.workdir.is_empty() {
+                        spec.workdir = "/app".to_string();
+                    }
+                    if spec.entrypoint.is_empty() {
+                        spec.entrypoint = "/bin/bash".to_string();
+                    }
+                    let rendered = render_template("Dockerfile", DOCKERFILE_TEMPLATE, &spec)
+                        .expect("Failed to render Dockerfile");
+                    let path = generated_path(&output_dir, "Dockerfile");
+                    report_lint(&path, &spec, out);
+                    write_to_file(&path, &rendered, Some("Dockerfile"), out)?;
+                }
+                if let Some(spec) = project.compose {
+                    let path = generated_path(&output_dir, "compose.yaml");
+                    report_compose_lint(&path, &spec, &[], out);
+                    let rendered = render_template("docker-compose.yml", DOCKER_COMPOSE_TEMPLATE, &spec)
+                        .expect("Failed to render docker-compose.yml");
+                    write_to_file(&path, &rendered, Some("docker-compose.yml"), out)?;
+                }
+                if let Some(mut spec) = project.devcontainer {
+                    if spec.remote_user.is_empty() {
+                        spec.remote_user = "vscode".to_string();
+                    }
+                    if spec.customizations.settings.is_null() {
+                        spec.customizations.settings = default_devcontainer_settings();
+                    }
+                    let rendered = render_template("devcontainer.json", DEVCONTAINER_TEMPLATE, &spec)
+                        .expect("Failed to render devcontainer.json");
+                    write_to_file(&generated_path(&output_dir, "devcontainer.json"), &rendered, Some("devcontainer.json"), out)?;
+                }
+                if let Some(mut spec) = project.bake {
+                    validate_hcl_identifier(&spec.group_name, "group")?;
+                    for target in &mut spec.targets {
+                        validate_hcl_identifier(&target.name, "target")?;
+                        target.context = normalize_context(&target.context);
+                        if target.dockerfile_inline.is_some() {
+                            if !target.dockerfile.is_empty() {
+                                return Err(invalid_input(format!(
+                                    "{}: bake target '{}' sets both dockerfile and dockerfile_inline",
+                                    from_file, target.name
+                                )));
+                            }
+                        } else if target.dockerfile.is_empty() {
+                            target.dockerfile = "./Dockerfile".to_string();
+                        }
+                    }
+                    for (name, _) in &spec.variables {
+                        validate_hcl_identifier(name, "variable")?;
+                    }
+                    let rendered = render_template("docker-bake.hcl", DOCKER_BAKE_TEMPLATE, &spec)
+                        .expect("Failed to render docker-bake.hcl");
+                    write_to_file(&generated_path(&output_dir, "docker-bake.hcl"), &rendered, Some("docker-bake.hcl"), out)?;
+                }
+                Ok(())
+            }
+            Self::Nomad { from_file, job, datacenter, output } => {
+                let contents = std::fs::read_to_string(&from_file)?;
+                let project: ProjectSpec = serde_yaml::from_str(&contents)
+                    .map_err(|e| invalid_input(format!("{}: {}", from_file, e)))?;
+                let Some(compose) = project.compose else {
+                    return Err(invalid_input(format!("{} has no compose section to convert", from_file)));
+                };
+                report_compose_lint(Path::new(&from_file), &compose, &[], out);
+                let job = job.or_else(|| compose.project_name.clone()).unwrap_or_else(|| "app".to_string());
+                let spec = nomad_job(&compose, job, datacenter)?;
+                let rendered = render_template("job.nomad.hcl", NOMAD_TEMPLATE, &spec)
+                    .expect("Failed to render Nomad job");
+                write_to_file(Path::new(&output), &rendered, Some("job.nomad.hcl"), out)
+            }
+            Self::Templates { action: TemplatesAction::List } => {
+                let width = TEMPLATES.iter().map(|t| t.name.len()).max().unwrap_or_default();
+                for template in TEMPLATES {
+                    println!("{:width$}  {}", template.name, template.description, width = width);
+                }
+                Ok(())
+            }
+            Self::Templates { action: TemplatesAction::Show { name } } => {
+                let Some(template) = TEMPLATES.iter().find(|t| t.name == name) else {
+                    let names: Vec<&str> = TEMPLATES.iter().map(|t| t.name).collect();
+                    return Err(invalid_input(format!(
+                        "unknown template '{}' (available: {})",
+                        name,
+                        names.join(", ")
+                    )));
+                };
+                print!("{}", template.source.trim_start_matches('\n'));
+                Ok(())
+            }
+            Self::Clean { output_dir, force } => {
+                if out.check || out.archive {
+                    return Err(invalid_input("clean does not support --check or --archive".to_string()));
+                }
+                let mut generated = Vec::new();
+                // Dockerfiles renamed with --dockerfile-name (`Dockerfile.api`) are checked like `Dockerfile`
+                let renamed: Vec<String> = std::fs::read_dir(&output_dir)
+                    .map(|entries| {
+                        let mut names: Vec<String> = entries
+                            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+                            .filter(|name| name.starts_with("Dockerfile."))
+                            .collect();
+                        names.sort();
+                        names
+                    })
+                    .unwrap_or_default();
+                let candidates = GENERATED_FILES.iter().map(|file| (file.name, file.is_generated)).chain(
+                    renamed.iter().map(|name| (name.as_str(), generated_file("Dockerfile").is_generated)),
+                );
+                for (name, is_generated) in candidates {
+                    let path = Path::new(&output_dir).join(name);
+                    let Ok(contents) = std::fs::read_to_string(&path) else {
+                        continue;
+                    };
+                    if is_generated(&contents) {
+                        generated.push(path);
+                    } else {
+                        eprintln!("warning: leaving {} in place; it was not generated by configgen", path.display());
+                    }
+                }
+                if generated.is_empty() {
+                    println!("No generated files found in: {}", output_dir);
+                    return Ok(());
+                }
+
+                if out.dry_run {
+                    for path in &generated {
+                        println!("Would remove: {}", path.display());
+                    }
+                    return Ok(());
+                }
+                println!("Generated files in {}:", output_dir);
+                for path in &generated {
+                    println!("  {}", path.display());
+                }
+                if !force && !confirm("Remove these files?")? {
+                    println!("Nothing removed.");
+                    return Ok(());
+                }
+                for path in &generated {
+                    std::fs::remove_file(path)?;
+                    println!("Removed: {}", path.display());
+                    // Subdirectories like `.devcontainer` go too once they're empty
+                    if let Some(parent) = path.parent().filter(|p| *p != Path::new(&output_dir)) {
+                        let _ = std::fs::remove_dir(parent);
+                    }
+                }
+                // Drop the directory `init` created, but only once nothing else is left in it
+                if output_dir != "." && std::fs::remove_dir(&output_dir).is_ok() {
+                    println!("Removed empty directory: {}", output_dir);
+                }
+                Ok(())
+            }
+            Self::Validate { file } => {
+                let contents = std::fs::read_to_string(&file)?;
+                let is_dockerfile = Path::new(&file)
+                    .file_name()
+                    .is_some_and(|n| n.to_string_lossy().contains("Dockerfile"));
+                let findings = if is_dockerfile {
+                    validate_dockerfile(&contents)
+                } else {
+                    validate_compose_file(&contents)
+                };
+
+                if findings.is_empty() {
+                    println!("No issues found in {}", file);
+                    return Ok(());
+                }
+                for finding in &findings {
+                    println!("{}: {}", file, finding);
+                }
+                Err(invalid_input(format!("{} issue(s) found in {}", findings.len(), file)))
+            }
+            Self::Dockerfile { base_image, maintainer, packages, packages_file, snapshot_date, apt_layers, recommends, no_recommends: _, labels_from_git, oci_metadata, oci_title, oci_description, oci_version, oci_licenses, oci_created, base_image_from_lock, digest, platform, amd64, arm64, context, bake, compose, workdir, user, entrypoint, output } => {
+                let base_image = match (digest, base_image_from_lock) {
+                    (Some(digest), _) => pin_digest(&base_image, &digest)?,
+                    (None, Some(lockfile)) => pin_digest(&base_image, &locked_digest(&lockfile, &base_image)?)?,
+                    (None, None) => base_image,
+                };
+                let mut package_list = match (&packages, &packages_file) {
+                    (None, None) => vec!["curl".to_string(), "git".to_string()],
+                    _ => packages.as_deref().map(parse_list).unwrap_or_default(),
+                };
+                if let Some(path) = packages_file {
+                    for package in read_packages_file(&path)? {
+                        if !package_list.contains(&package) {
+                            package_list.push(package);
+                        }
+                    }
+                }
+
+                let snapshot_date = snapshot_date.as_deref().map(snapshot_timestamp).transpose()?;
+                if snapshot_date.is_some() && ["ubuntu", "alpine"].contains(&image_name(&base_image)) {
+                    warning!("--snapshot-date pins Debian mirrors, which '{}' doesn't use", base_image);
+                }
+
+                let platforms = match platform {
+                    Some(platform) => vec![platform],
+                    None => shortcut_platforms(amd64, arm64),
+                };
+                if platforms.len() > 1 && bake.is_none() {
+                    return Err(invalid_input(
+                        "a Dockerfile's FROM takes one platform; add --bake to build for both --amd64 and --arm64".to_string(),
+                    ));
+                }
+                let spec = DockerfileSpec {
+                    platform: match platforms.as_slice() {
+                        [platform] => Some(platform.clone()),
+                        _ => None,
+                    },
+                    base_image,
+                    maintainer,
+                    packages: package_list,
+                    workdir,
+                    entrypoint,
+                    user,
+                    snapshot_date,
+                    apt_layers,
+                    install_recommends: recommends,
+                    labels: if labels_from_git { git_source_labels()? } else { Vec::new() },
+                    oci_labels: if oci_metadata {
+                        let detected = detect_project_metadata();
+                        let title = oci_title.or(detected.name).or_else(|| {
+                            std::env::current_dir().ok()?.file_name().map(|name| name.to_string_lossy().into_owned())
+                        });
+                        let created = match oci_created {
+                            Some(created) if is_rfc3339(&created) => created,
+                            Some(created) => {
+                                return Err(invalid_input(format!(
+                                    "--oci-created must be an RFC 3339 timestamp like 2024-01-01T00:00:00Z, got '{}'",
+                                    created
+                                )))
+                            }
+                            None => rfc3339_now(),
+                        };
+                        oci_metadata_labels([
+                            ("title", title),
+                            ("description", oci_description.or(detected.description)),
+                            ("version", oci_version.or(detected.version)),
+                            ("created", Some(created)),
+                            ("licenses", oci_licenses.or(detected.license)),
+                        ])
+                    } else {
+                        Vec::new()
+                    },
+                    entrypoint_mode: *ENTRYPOINT_MODE.lock().unwrap(),
+                    ..Default::default()
+                };
+                let rendered = render_template("Dockerfile", DOCKERFILE_TEMPLATE, &spec)
+                    .expect("Failed to render Dockerfile");
+                report_lint(Path::new(&output), &spec, out);
+                write_to_file(Path::new(&output), &rendered, Some("Dockerfile"), out)?;
+
+                if context.is_some() && bake.is_none() && compose.is_none() {
+                    warning!("--context only applies to the build config written by --bake or --compose");
+                }
+                let context = normalize_context(context.as_deref().unwrap_or("."));
+                let dockerfile = dockerfile_in_context(&output, &context);
+                if let Some(bake_output) = bake {
+                    let bake_spec = DockerBakeSpec {
+                        group_name: "default".to_string(),
+                        targets: vec![BakeTarget {
+                            name: "app".to_string(),
+                            context: context.clone(),
+                            dockerfile: dockerfile.clone(),
+                            tags: vec!["app:latest".to_string()],
+                            platforms,
+                            ..Default::default()
+                        }],
+                        variables: Vec::new(),
+                    };
+                    let rendered = render_template("docker-bake.hcl", DOCKER_BAKE_TEMPLATE, &bake_spec)
+                        .expect("Failed to render docker-bake.hcl");
+                    write_to_file(Path::new(&bake_output), &rendered, Some("docker-bake.hcl"), out)?;
+                }
+                if let Some(compose_output) = compose {
+                    let compose_spec = DockerComposeSpec {
+                        services: vec![ServiceSpec {
+                            name: "app".to_string(),
+                            image: "app:latest".to_string(),
+                            build: Some(BuildConfig {
+                                dockerfile: (dockerfile != "Dockerfile").then_some(dockerfile),
+                                ..BuildConfig::new(&context)
+                            }),
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    };
+                    let rendered = render_template("docker-compose.yml", DOCKER_COMPOSE_TEMPLATE, &compose_spec)
+                        .expect("Failed to render docker-compose.yml");
+                    report_compose_lint(Path::new(&compose_output), &compose_spec, &[], out);
+                    write_to_file(Path::new(&compose_output), &rendered, Some("docker-compose.yml"), out)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// True when CONFIGGEN_NONINTERACTIVE is set to anything other than empty or "0".
+fn noninteractive_env() -> bool {
+    std::env::var_os("CONFIGGEN_NONINTERACTIVE").is_some_and(|v| !v.is_empty() && v != "0")
+}
+
+fn run() -> io::Result<()> {
+    // Interactive mode only starts for a bare invocation with no arguments at all
+    let bare = std::env::args().len() == 1;
+    let cli = Cli::parse();
+    VERBOSE.store(cli.verbose, Ordering::Relaxed);
+    STRICT.store(cli.strict, Ordering::Relaxed);
+    *ENTRYPOINT_MODE.lock().unwrap() = cli.entrypoint_mode;
+    if let Some(command) = &cli.post_format {
+        let _ = POST_FORMAT.set(command.clone());
+    }
+    let out = OutputOptions {
+        policy: cli.overwrite_policy,
+        check: cli.check,
+        lint: cli.lint,
+        archive: cli.archive.is_some(),
+        compose_lint: cli.compose_lint,
+        dry_run: cli.dry_run,
+        diff: cli.diff,
+    };
+    let Some(command) = cli.command else {
+        if bare && !noninteractive_env() {
+            interactive_cli(out)?;
+            return flush_writes();
+        }
+        eprintln!("{}", Cli::command().render_usage());
+        let reason = if cli.no_interactive {
+            "--no-interactive was given"
+        } else if noninteractive_env() {
+            "CONFIGGEN_NONINTERACTIVE is set"
+        } else {
+            "only options were given"
+        };
+        return Err(invalid_input(format!(
+            "no subcommand given. Interactive mode only starts when configgen is run with no arguments \
+             and CONFIGGEN_NONINTERACTIVE is unset; here {}. Pass a subcommand (see --help)",
+            reason
+        )));
+    };
+
+    let output_dir = command.output_dir();
+    command.execute(out)?;
+    flush_writes()?;
+
+    let warnings = WARNINGS.load(Ordering::Relaxed);
+    if cli.strict && warnings > 0 {
+        return Err(io::Error::other(format!("{} warning(s) treated as errors (--strict)", warnings)));
+    }
+    if let Some(archive) = &cli.archive {
+        write_archive(Path::new(archive))?;
+    }
+    if let Some(manifest) = &cli.manifest {
+        write_manifest(&output_dir.join(manifest), out)?;
+    }
+
+    let stale = STALE_FILES.load(Ordering::Relaxed);
+    if stale > 0 {
+        return Err(io::Error::other(format!("{} generated file(s) are out of date", stale)));
+    }
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dockerfile_spec() -> DockerfileSpec {
+        DockerfileSpec {
+            base_image: "debian:bookworm".to_string(),
+            maintainer: "Test <test@example.com>".to_string(),
+            packages: vec!["curl".to_string()],
+            workdir: "/app".to_string(),
+            entrypoint: "/bin/bash".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn render_dockerfile(spec: &DockerfileSpec) -> String {
+        render_template("Dockerfile", DOCKERFILE_TEMPLATE, spec).unwrap()
+    }
+
+    #[test]
+    fn recommends_flag_controls_no_install_recommends() {
+        let parse = |args: &[&str]| match Cli::try_parse_from(args).unwrap().command {
+            Some(Commands::Dockerfile { recommends, .. }) => recommends,
+            _ => unreachable!(),
+        };
+        assert!(!parse(&["configgen", "dockerfile", "-o", "Dockerfile"]));
+        assert!(parse(&["configgen", "dockerfile", "--recommends", "-o", "Dockerfile"]));
+        assert!(!parse(&["configgen", "dockerfile", "--no-recommends", "-o", "Dockerfile"]));
+        assert!(!parse(&["configgen", "dockerfile", "--recommends", "--no-recommends", "-o", "Dockerfile"]));
+        assert!(parse(&["configgen", "dockerfile", "--no-recommends", "--recommends", "-o", "Dockerfile"]));
+
+        let rendered = render_dockerfile(&dockerfile_spec());
+        assert!(rendered.contains("apt-get install -y --no-install-recommends"));
+        let rendered = render_dockerfile(&DockerfileSpec { install_recommends: true, ..dockerfile_spec() });
+        assert!(rendered.contains("apt-get install -y"));
+        assert!(!rendered.contains("--no-install-recommends"));
+    }
+
+    #[test]
+    fn entrypoint_mode_selects_exec_or_shell_form() {
+        let parse = |args: &[&str]| Cli::try_parse_from(args).unwrap().entrypoint_mode;
+        assert_eq!(parse(&["configgen", "dockerfile", "-o", "Dockerfile"]), EntrypointMode::Exec);
+        assert_eq!(parse(&["configgen", "--entrypoint-mode", "shell", "dockerfile"]), EntrypointMode::Shell);
+        assert_eq!(parse(&["configgen", "init", "--entrypoint-mode", "shell"]), EntrypointMode::Shell);
+
+        let rendered = render_dockerfile(&dockerfile_spec());
+        assert!(rendered.contains("ENTRYPOINT [\"/bin/bash\"]"));
+        let rendered = render_dockerfile(&DockerfileSpec { entrypoint_mode: EntrypointMode::Shell, ..dockerfile_spec() });
+        assert!(rendered.lines().any(|line| line == "ENTRYPOINT /bin/bash"));
+        assert!(!rendered.contains("ENTRYPOINT ["));
+    }
+
+    /// Fresh scratch directory for a test that runs a whole command.
+    fn test_dir(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("configgen-test-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.to_string_lossy().into_owned()
+    }
+
+    /// Commands share the queue of pending writes, so tests that run them take turns.
+    static COMMAND_LOCK: Mutex<()> = Mutex::new(());
+
+    /// Runs a command like `run` does, returning its error before or while writing.
+    fn try_run_command(args: &[&str]) -> io::Result<()> {
+        let _guard = COMMAND_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let cli = Cli::try_parse_from(args).unwrap();
+        STRICT.store(cli.strict, Ordering::Relaxed);
+        *ENTRYPOINT_MODE.lock().unwrap() = cli.entrypoint_mode;
+        WARNINGS.store(0, Ordering::Relaxed);
+        let result = cli.command.unwrap().execute(OutputOptions::default()).and_then(|()| flush_writes());
+        PENDING_WRITES.lock().unwrap().clear();
+        result
+    }
+
+    fn run_command(args: &[&str]) {
+        try_run_command(args).unwrap();
+    }
+
+    #[test]
+    fn renamed_dockerfile_is_referenced_by_bake_and_cleaned() {
+        let dir = test_dir("renamed-dockerfile");
+        run_command(&["configgen", "init", "-o", &dir, "--dockerfile-name", "Dockerfile.api", "--bake"]);
+        let dockerfile = Path::new(&dir).join("Dockerfile.api");
+        assert!(dockerfile.exists());
+        assert!(!Path::new(&dir).join("Dockerfile").exists());
+        let bake = std::fs::read_to_string(Path::new(&dir).join("docker-bake.hcl")).unwrap();
+        assert!(bake.contains("dockerfile = \"Dockerfile.api\""), "{}", bake);
+
+        run_command(&["configgen", "clean", "-o", &dir, "--force"]);
+        assert!(!dockerfile.exists());
+    }
+
+    #[test]
+    fn init_bake_rejects_invalid_target_names() {
+        for name in ["my app", "1app"] {
+            let dir = test_dir("init-bake-identifier");
+            let error = try_run_command(&["configgen", "init", "-o", &dir, "--name", name, "--bake"]).unwrap_err();
+            assert!(error.to_string().contains("not a valid HCL identifier"), "{}", error);
+            assert!(!Path::new(&dir).join("Dockerfile").exists());
+        }
+        assert!(validate_hcl_identifier("my app", "target").is_err());
+        assert!(validate_hcl_identifier("1app", "target").is_err());
+        assert!(validate_hcl_identifier("my-app_2", "target").is_ok());
+    }
+
+    fn compose_spec() -> DockerComposeSpec {
+        DockerComposeSpec {
+            project_name: Some("demo".to_string()),
+            services: vec![
+                ServiceSpec {
+                    name: "web".to_string(),
+                    build: Some(BuildConfig::new(".")),
+                    ports: vec!["8000:8000".to_string()],
+                    depends_on: vec![Dependency {
+                        condition: Some("service_healthy".to_string()),
+                        restart: true,
+                        ..Dependency::new("db")
+                    }],
+                    environment: vec![("DEBUG".to_string(), "1".to_string())],
+                    shared_env: true,
+                    ..Default::default()
+                },
+                ServiceSpec {
+                    name: "db".to_string(),
+                    image: "postgres:16".to_string(),
+                    volumes: vec!["dbdata:/var/lib/postgresql/data".to_string()],
+                    healthcheck: known_healthcheck("postgres:16"),
+                    ..Default::default()
+                },
+            ],
+            common_env: vec![("TZ".to_string(), "UTC".to_string())],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn json_compose_round_trips_through_the_compose_parser() {
+        let spec = compose_spec();
+        let json = ComposeFormat::Json.render(&spec).unwrap();
+        assert!(validate_compose_file(&json).is_empty(), "{}", json);
+
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let mut yaml: serde_yaml::Value = serde_yaml::from_str(&ComposeFormat::Yaml.render(&spec).unwrap()).unwrap();
+        yaml.apply_merge().unwrap();
+        assert_eq!(parsed, serde_json::to_value(&yaml).unwrap());
+
+        let file: ComposeFile = serde_yaml::from_str(&json).unwrap();
+        let mut findings = Vec::new();
+        let services: Vec<ServiceSpec> =
+            file.services.unwrap().0.iter().map(|(name, value)| service_from_yaml(name, value, &mut findings)).collect();
+        assert!(findings.is_empty());
+        let web = services.iter().find(|s| s.name == "web").unwrap();
+        assert_eq!(web.ports, ["8000:8000"]);
+        assert_eq!(web.depends_on[0].service, "db");
+        assert_eq!(web.depends_on[0].condition.as_deref(), Some("service_healthy"));
+        assert!(web.depends_on[0].restart);
+        assert_eq!(web.environment, [("DEBUG".to_string(), "1".to_string()), ("TZ".to_string(), "UTC".to_string())]);
+        let db = services.iter().find(|s| s.name == "db").unwrap();
+        assert_eq!(db.image, "postgres:16");
+        assert_eq!(db.volumes, ["dbdata:/var/lib/postgresql/data"]);
+    }
+
+    #[test]
+    fn json_and_yaml_compose_round_trip_to_the_same_value() {
+        let args = [
+            "--services", "web,db,cache",
+            "--ports", "web=8000:8000,db=5432:5432",
+            "--env", "TZ=UTC,web:DEBUG=1,db:POSTGRES_PASSWORD=secret",
+            "--share-env", "web,db",
+            "--depends-on", "web:db:service_healthy:restart|cache",
+            "--cpus", "web:0.5", "--memory", "db:1g",
+            "--build-target", "web:dev", "--build-arg", "web:VERSION=1",
+            "--watch", "web:sync:./src:/app/src",
+            "--x", "x-owner:team-a", "--x", "web:x-tier:frontend",
+            "--config-inline", "app:port=8000",
+            "--project-name", "demo",
+        ];
+        for limits_style in ["deploy", "inline"] {
+            let extra = ["--limits-style", limits_style];
+            let yaml = compose_output(&format!("round-trip-yaml-{}", limits_style), &[&args[..], &extra].concat());
+            let json = compose_output(&format!("round-trip-json-{}", limits_style), &[&args[..], &extra, &["--format", "json"]].concat());
+            let mut from_yaml: serde_yaml::Value = serde_yaml::from_str(&yaml).unwrap();
+            from_yaml.apply_merge().unwrap();
+            let from_json: serde_json::Value = serde_json::from_str(&json).unwrap();
+            assert_eq!(serde_json::to_value(&from_yaml).unwrap(), from_json, "{}", yaml);
+            assert_eq!(from_json["services"]["web"]["environment"]["TZ"], "UTC", "{}", json);
+        }
+    }
+
+    #[test]
+    fn manifest_paths_are_relative_to_the_manifest() {
+        assert_eq!(manifest_path(Path::new("proj/Dockerfile"), Path::new("proj")), "Dockerfile");
+        assert_eq!(manifest_path(Path::new("./proj/.devcontainer/docker-compose.yml"), Path::new("proj")), ".devcontainer/docker-compose.yml");
+        assert_eq!(manifest_path(Path::new("Dockerfile"), Path::new(".")), "Dockerfile");
+        assert_eq!(manifest_path(Path::new("other/Dockerfile"), Path::new("proj")), "other/Dockerfile");
+
+        let output_dir = |args: &[&str]| Cli::try_parse_from(args).unwrap().command.unwrap().output_dir();
+        assert_eq!(output_dir(&["configgen", "init", "-o", "proj"]), Path::new("proj"));
+        assert_eq!(output_dir(&["configgen", "dockerfile", "-o", "docker/Dockerfile"]), Path::new("docker"));
+        assert_eq!(output_dir(&["configgen", "dockerfile", "-o", "Dockerfile"]), Path::new("."));
+    }
+
+    /// Runs `configgen compose` with `args` and returns the compose file it wrote.
+    fn compose_output(name: &str, args: &[&str]) -> String {
+        let dir = test_dir(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = Path::new(&dir).join("compose.yaml").to_string_lossy().into_owned();
+        let mut command = vec!["configgen", "compose", "--no-interactive", "-o", &output];
+        command.extend_from_slice(args);
+        run_command(&command);
+        std::fs::read_to_string(&output).unwrap()
+    }
+
+    #[test]
+    fn pull_policy_renders_each_value() {
+        for policy in PULL_POLICIES {
+            let compose = compose_output(&format!("pull-policy-{}", policy), &["--services", "web", "--pull-policy", &format!("web:{}", policy)]);
+            assert!(compose.contains(&format!("    pull_policy: {}\n", policy)), "{}", compose);
+        }
+        let compose = compose_output("pull-policy-unset", &["--services", "web"]);
+        assert!(!compose.contains("pull_policy"));
+    }
+
+    #[test]
+    fn pull_policy_rejects_unknown_values() {
+        let dir = test_dir("pull-policy-invalid");
+        let output = Path::new(&dir).join("compose.yaml").to_string_lossy().into_owned();
+        let error =
+            try_run_command(&["configgen", "compose", "--no-interactive", "-o", &output, "--services", "web", "--pull-policy", "web:sometimes"])
+                .unwrap_err();
+        assert!(error.to_string().contains("must be one of always, never, missing, build"), "{}", error);
+    }
+
+    #[test]
+    fn pull_policy_build_counts_builds_added_by_later_flags() {
+        let dir = test_dir("pull-policy-late-build");
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = Path::new(&dir).join("compose.yaml").to_string_lossy().into_owned();
+        let compose = |args: &[&str]| {
+            let command = [&["configgen", "--strict", "compose", "--no-interactive", "-o", &output, "--services", "app", "--pull-policy", "app:build"], args].concat();
+            try_run_command(&command)
+        };
 
-                let env_input = prompt("Environment variables (KEY=VALUE,KEY2=VALUE2)")?;
-                let environment: Vec<(String, String)> = if env_input.is_empty() {
-                    Vec::new()
-                } else {
-                    env_input
-                        .split(',')
-                        .filter_map(|pair| {
-                            pair.split_once('=').map(|(k, v)| {
-                                (k.trim().to_string(), v.trim().to_string())
-                            })
-                        })
-                        .collect()
-                };
+        compose(&["--build-target", "app:dev"]).unwrap();
+        let written = std::fs::read_to_string(&output).unwrap();
+        assert!(written.contains("    pull_policy: build\n") && written.contains("      target: dev\n"), "{}", written);
+        compose(&["--build-arg", "app:VERSION=1"]).unwrap();
+        let override_output = Path::new(&dir).join("compose.override.yaml").to_string_lossy().into_owned();
+        compose(&["--override-output", &override_output, "--dev-build", "app"]).unwrap();
 
-                services.push(ServiceSpec {
-                    name,
-                    image,
-                    ports,
-                    depends_on: Vec::new(),
-                    environment,
-                    volumes,
-                });
+        let err = compose(&[]).unwrap_err();
+        assert!(err.to_string().contains("1 warning(s) treated as errors (--strict)"), "{}", err);
+    }
 
-                if !confirm("Add another service?")? {
-                    break;
-                }
-            }
+    #[test]
+    fn watch_counts_builds_added_by_later_flags() {
+        let dir = test_dir("watch-late-build");
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = Path::new(&dir).join("compose.yaml").to_string_lossy().into_owned();
+        let compose = |args: &[&str]| {
+            let command = [&["configgen", "--strict", "compose", "--no-interactive", "-o", &output, "--services", "app", "--watch", "app:sync:./src:/app/src"], args].concat();
+            try_run_command(&command)
+        };
 
-            let mut networks_map = HashMap::new();
-            if confirm("Add networks?")? {
-                loop {
-                    let network = prompt("Network name")?;
-                    networks_map.insert(network, NetworkConfig {
-                        driver: "bridge".to_string(),
-                    });
-                    if !confirm("Add another network?")? {
-                        break;
-                    }
-                }
-            }
+        compose(&["--build-target", "app:dev"]).unwrap();
+        let written = std::fs::read_to_string(&output).unwrap();
+        assert!(written.contains("    develop:\n      watch:\n") && written.contains("      target: dev\n"), "{}", written);
 
-            let output = prompt("Output filename (default: docker-compose.yml)")?;
-            let output = if output.is_empty() { "docker-compose.yml".to_string() } else { output };
+        let err = compose(&[]).unwrap_err();
+        assert!(err.to_string().contains("1 warning(s) treated as errors (--strict)"), "{}", err);
+    }
 
-            let spec = DockerComposeSpec {
-                services,
-                networks: networks_map,
-            };
-            let rendered = render_template(DOCKER_COMPOSE_TEMPLATE, &spec).expect("Failed to render docker-compose.yml");
-            write_to_file(Path::new(&output), &rendered)?;
-        }
-        2 => {
-            // Dev Container
-            let name = prompt("Container name (default: Dev Container)")?;
-            let name = if name.is_empty() { "Dev Container".to_string() } else { name };
-            
-            let dockerfile = prompt("Dockerfile path (default: ./Dockerfile)")?;
-            let dockerfile = if dockerfile.is_empty() { "./Dockerfile".to_string() } else { dockerfile };
-            
-            let remote_user = prompt("Remote user (default: vscode)")?;
-            let remote_user = if remote_user.is_empty() { "vscode".to_string() } else { remote_user };
-            
-            let extensions = prompt("VSCode extensions (comma-separated)")?;
-            let extensions = if extensions.is_empty() {
-                vec!["ms-azuretools.vscode-docker".to_string()]
-            } else {
-                extensions.split(',').map(|s| s.trim().to_string()).collect()
-            };
+    /// Runs `configgen init` with `args` and returns the generated `file`.
+    fn init_output(name: &str, args: &[&str], file: &str) -> String {
+        let dir = test_dir(name);
+        let mut command = vec!["configgen", "init", "-o", &dir];
+        command.extend_from_slice(args);
+        run_command(&command);
+        std::fs::read_to_string(Path::new(&dir).join(file)).unwrap()
+    }
 
-            let output = prompt("Output filename (default: devcontainer.json)")?;
-            let output = if output.is_empty() { "devcontainer.json".to_string() } else { output };
+    #[test]
+    fn no_default_network_leaves_out_networks() {
+        let compose = init_output("default-network", &["--database", "postgres"], "compose.yaml");
+        assert!(compose.contains("\nnetworks:\n  app_network:"), "{}", compose);
 
-            let spec = DevContainerSpec {
-                name,
-                dockerfile_path: dockerfile,
-                remote_user,
-                customizations: DevContainerCustomizations {
-                    vscode_extensions: extensions,
-                    settings: json!({
-                        "editor.formatOnSave": true,
-                        "terminal.integrated.shell.linux": "/bin/bash"
-                    }),
-                },
-            };
-            let rendered = render_template(DEVCONTAINER_TEMPLATE, &spec).expect("Failed to render devcontainer.json");
-            write_to_file(Path::new(&output), &rendered)?;
+        let compose = init_output("no-default-network", &["--database", "postgres", "--no-default-network"], "compose.yaml");
+        assert!(!compose.contains("networks:"), "{}", compose);
+        assert!(!compose.contains("app_network"), "{}", compose);
+    }
+
+    #[test]
+    fn known_images_get_their_healthcheck() {
+        let cases = [
+            ("postgres:16", "pg_isready -U $$POSTGRES_USER"),
+            ("mysql:8.4", "mysqladmin"),
+            ("mongo:7.0", "mongosh"),
+            ("redis:latest", "redis-cli"),
+            ("docker.elastic.co/elasticsearch/elasticsearch:8.13.0", "_cluster/health"),
+        ];
+        for (image, command) in cases {
+            let healthcheck = known_healthcheck(image).unwrap_or_else(|| panic!("no healthcheck for {}", image));
+            assert!(healthcheck.test.iter().any(|arg| arg.contains(command)), "{}: {:?}", image, healthcheck.test);
         }
-        3 => {
-            // Docker Bake
-            let mut targets = Vec::new();
-            loop {
-                println!("\n=== Add Target ===");
-                let name = prompt("Target name")?;
-                let context = prompt("Context (default: ./)")?;
-                let context = if context.is_empty() { "./".to_string() } else { context };
-                
-                let dockerfile = prompt("Dockerfile path (default: ./Dockerfile)")?;
-                let dockerfile = if dockerfile.is_empty() { "./Dockerfile".to_string() } else { dockerfile };
-                
-                let tag = prompt("Tag (default: latest)")?;
-                let tag = if tag.is_empty() { "latest".to_string() } else { tag };
+        assert!(known_healthcheck("nginx:latest").is_none());
 
-                targets.push(BakeTarget {
-                    name: name.clone(),
-                    context,
-                    dockerfile,
-                    tags: vec![format!("{}:{}", name, tag)],
-                });
+        let compose = init_output("healthchecks", &["--database", "postgres", "--services", "redis,elasticsearch"], "compose.yaml");
+        assert_eq!(compose.matches("    healthcheck:\n").count(), 3, "{}", compose);
+        assert!(compose.contains("condition: service_healthy"), "{}", compose);
+        // The check is plain HTTP, which only answers once 8.x security is off
+        let parsed: serde_yaml::Value = serde_yaml::from_str(&compose).unwrap();
+        let elasticsearch = &parsed["services"]["elasticsearch"];
+        assert!(elasticsearch["healthcheck"]["test"][1].as_str().unwrap().contains("http://localhost:9200"));
+        assert_eq!(elasticsearch["environment"]["xpack.security.enabled"].as_str(), Some("false"), "{}", compose);
+        let compose = init_output("no-healthchecks", &["--database", "postgres", "--no-healthchecks"], "compose.yaml");
+        assert!(!compose.contains("healthcheck:"), "{}", compose);
+    }
 
-                if !confirm("Add another target?")? {
-                    break;
-                }
-            }
+    #[test]
+    fn depends_on_restart_renders_long_form() {
+        let dependency = parse_dependency("db:service_healthy:restart").unwrap();
+        assert_eq!(dependency.condition.as_deref(), Some("service_healthy"));
+        assert!(dependency.restart);
+        let dependency = parse_dependency("db:restart").unwrap();
+        assert_eq!(dependency.condition.as_deref(), Some("service_started"));
+        assert!(parse_dependency("db:restart:service_healthy").is_err());
 
-            let group = prompt("Group name (default: default)")?;
-            let group = if group.is_empty() { "default".to_string() } else { group };
+        let compose = compose_output("depends-on-restart", &["--services", "web,db", "--depends-on", "web:db:service_healthy:restart"]);
+        assert!(
+            compose.contains("    depends_on:\n      db:\n        condition: service_healthy\n        restart: true\n"),
+            "{}",
+            compose
+        );
+    }
 
-            let output = prompt("Output filename (default: docker-bake.hcl)")?;
-            let output = if output.is_empty() { "docker-bake.hcl".to_string() } else { output };
+    #[test]
+    fn project_name_renders_top_level_name() {
+        let compose = compose_output("project-name", &["--services", "web", "--project-name", "shop-api"]);
+        assert!(compose.contains("version: '3.8'\nname: shop-api\n"), "{}", compose);
+        let compose = compose_output("no-project-name", &["--services", "web"]);
+        assert!(!compose.contains("\nname:"), "{}", compose);
 
-            let spec = DockerBakeSpec {
-                group_name: group,
-                targets,
-            };
-            let rendered = render_template(DOCKER_BAKE_TEMPLATE, &spec).expect("Failed to render docker-bake.hcl");
-            write_to_file(Path::new(&output), &rendered)?;
-        }
-        4 => {
-            // Complete Environment
-            let name = prompt("Project name")?;
-            
-            let language_options = ["Python", "Node.js", "Rust", "Other"];
-            let language_idx = select_option(&language_options, "Select programming language:")?;
-            let language = language_options[language_idx].to_lowercase();
+        assert!(validate_project_name("Shop API").is_err());
+        assert_eq!(sanitize_project_name("Shop API"), "shop-api");
+        let compose = init_output("init-project-name", &["--name", "My_App"], "compose.yaml");
+        assert!(compose.contains("\nname: my_app\n"), "{}", compose);
+    }
 
-            let db_options = ["None", "PostgreSQL", "MySQL", "MongoDB"];
-            let db_idx = select_option(&db_options, "Select database:")?;
-            let database = if db_idx == 0 {
-                None
-            } else {
-                Some(db_options[db_idx].to_lowercase())
-            };
+    #[test]
+    fn depends_on_style_selects_list_or_map_form() {
+        let args = ["--services", "web,db", "--depends-on", "web:db"];
+        let short = "    depends_on:\n      - db\n";
+        let long = "    depends_on:\n      db:\n        condition: service_started\n";
 
-            let service_options = ["None", "Redis", "Elasticsearch"];
-            let mut selected_services = Vec::new();
-            while confirm("Add additional service?")? {
-                let service_idx = select_option(&service_options, "Select service:")?;
-                if service_idx > 0 {
-                    selected_services.push(service_options[service_idx].to_lowercase());
-                }
-            }
-            let services = if selected_services.is_empty() {
-                None
-            } else {
-                Some(selected_services.join(","))
-            };
+        let compose = compose_output("depends-on-default", &args);
+        assert!(compose.contains(short), "{}", compose);
+        let compose = compose_output("depends-on-long", &[&args[..], &["--depends-on-style", "long"]].concat());
+        assert!(compose.contains(long), "{}", compose);
+        let compose = compose_output("depends-on-short", &[&args[..], &["--depends-on-style", "short"]].concat());
+        assert!(compose.contains(short), "{}", compose);
 
-            let output_dir = prompt("Output directory (default: .)")?;
-            let output_dir = if output_dir.is_empty() { ".".to_string() } else { output_dir };
+        // A condition picks the long form unless short is forced, which drops it
+        let conditional = ["--services", "web,db", "--depends-on", "web:db:service_healthy"];
+        let compose = compose_output("depends-on-condition", &conditional);
+        assert!(compose.contains("      db:\n        condition: service_healthy\n"), "{}", compose);
+        let compose = compose_output("depends-on-condition-short", &[&conditional[..], &["--depends-on-style", "short"]].concat());
+        assert!(compose.contains(short), "{}", compose);
+    }
 
-            // Call the existing init implementation
-            Commands::Init {
-                name,
-                language,
-                database,
-                services,
-                output_dir,
-            }.execute()?;
-        }
-        _ => unreachable!(),
+    #[test]
+    fn depends_on_accepts_multiple_dependencies() {
+        let expected = "    depends_on:\n      - db\n      - redis\n";
+        let compose = compose_output("depends-on-pipe", &["--services", "web,db,redis", "--depends-on", "web:db|redis"]);
+        assert!(compose.contains(expected), "{}", compose);
+        let compose = compose_output("depends-on-repeated", &["--services", "web,db,redis", "--depends-on", "web:db,web:redis,web:db"]);
+        assert!(compose.contains(expected), "{}", compose);
+
+        let mut services: Vec<_> = ["web", "db", "redis"]
+            .iter()
+            .map(|name| ServiceSpec { name: name.to_string(), ..Default::default() })
+            .collect();
+        add_dependencies(&mut services, "web:db:service_healthy|redis,db:redis").unwrap();
+        let web: Vec<_> = services[0].depends_on.iter().map(|d| (d.service.as_str(), d.condition.as_deref())).collect();
+        assert_eq!(web, [("db", Some("service_healthy")), ("redis", None)]);
+        assert_eq!(services[1].depends_on.len(), 1);
     }
 
-    println!("\nConfiguration files generated successfully!");
-    Ok(())
-}
+    #[test]
+    fn regenerating_the_same_spec_is_byte_identical() {
+        let args = ["--services", "web,db", "--networks", "zeta,alpha,mid"];
+        let first = compose_output("stable-compose-1", &args);
+        let second = compose_output("stable-compose-2", &args);
+        assert_eq!(first, second);
+        assert!(first.contains("\nnetworks:\n  alpha:\n    driver: bridge\n  mid:\n    driver: bridge\n  zeta:\n"), "{}", first);
 
-// Add execute method to Commands enum
-impl Commands {
-    fn execute(self) -> io::Result<()> {
-        match self {
-            Self::Init { name, language, database, services, output_dir } => {
-                // Create output directory if it doesn't exist
-                std::fs::create_dir_all(&output_dir)?;
+        let devcontainer = |name: &str| {
+            let dir = test_dir(name);
+            std::fs::create_dir_all(&dir).unwrap();
+            let output = Path::new(&dir).join("devcontainer.json").to_string_lossy().into_owned();
+            run_command(&["configgen", "devcontainer", "-o", &output, "--setting", "z.last=1", "--setting", "a.first=true", "--container-env", "B=2", "--container-env", "A=1"]);
+            std::fs::read_to_string(output).unwrap()
+        };
+        assert_eq!(devcontainer("stable-devcontainer-1"), devcontainer("stable-devcontainer-2"));
+    }
 
-                // 1. Generate Dockerfile based on language
-                let (base_image, packages) = match language.as_str() {
-                    "python" => ("python:3.12-slim", "python3-pip,python3-dev,build-essential"),
-                    "node" => ("node:22-slim", "npm"),
-                    "rust" => ("rust:1.83-slim", "cargo"),
-                    _ => ("ubuntu:23.10", "curl,git"),
-                };
+    fn bake_output(name: &str, args: &[&str]) -> String {
+        let dir = test_dir(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = Path::new(&dir).join("docker-bake.hcl").to_string_lossy().into_owned();
+        let mut command = vec!["configgen", "bake", "-o", &output];
+        command.extend_from_slice(args);
+        run_command(&command);
+        std::fs::read_to_string(&output).unwrap()
+    }
 
-                let dockerfile_spec = DockerfileSpec {
-                    base_image: base_image.to_string(),
-                    maintainer: "Generated <generated@example.com>".to_string(),
-                    packages: packages.split(',').map(|s| s.trim().to_string()).collect(),
-                    workdir: "/app".to_string(),
-                    entrypoint: "/bin/bash".to_string(),
-                };
-                let dockerfile = render_template(DOCKERFILE_TEMPLATE, &dockerfile_spec)
-                    .expect("Failed to render Dockerfile");
-                write_to_file(&Path::new(&output_dir).join("Dockerfile"), &dockerfile)?;
+    #[test]
+    fn bake_arrays_have_no_trailing_comma() {
+        let bake = bake_output("bake-trailing-comma", &["--targets", "api,worker", "--tags", "api=latest|v1,worker=latest"]);
+        assert!(bake.contains("  targets = [\n    \"api\",\n    \"worker\"\n  ]\n"), "{}", bake);
+        assert!(bake.contains("  tags       = [\n    \"api:latest\",\n    \"api:v1\"\n  ]\n"), "{}", bake);
+        assert!(!bake.contains(",\n  ]"), "{}", bake);
+    }
 
-                // 2. Generate docker-compose.yml with services
-                let mut service_specs = Vec::new();
-                let mut networks_map = HashMap::new();
-                networks_map.insert("app_network".to_string(), NetworkConfig {
-                    driver: "bridge".to_string(),
-                });
+    #[test]
+    fn compact_mode_tightens_bake_and_devcontainer() {
+        let args = ["--targets", "api,worker", "--tags", "api=latest|v1,worker=latest"];
+        let compact = bake_output("bake-compact", &[&args[..], &["--compact"]].concat());
+        assert!(compact.contains("group \"default\" {\n  targets = [\"api\", \"worker\"]\n}\n"), "{}", compact);
+        assert!(compact.contains("  tags = [\"api:latest\", \"api:v1\"]\n"), "{}", compact);
+        assert!(!compact.contains("\n\n"), "{}", compact);
+        assert_eq!(compact, compact_hcl(&bake_output("bake-verbose", &args)));
 
-                // Add main app service
-                service_specs.push(ServiceSpec {
-                    name: name.clone(),
-                    image: format!("{}:latest", name),
-                    ports: vec!["8000:8000".to_string()],
-                    depends_on: Vec::new(),
-                    environment: Vec::new(),
-                    volumes: vec!["./:/app".to_string()],
-                });
+        let hcl = "target \"api\" {\n  dockerfile-inline = <<EOT\nFROM alpine\n\nRUN true\nEOT\n  tags       = [\n    \"a\",\n    \"b\"\n  ]\n}\n";
+        assert_eq!(compact_hcl(hcl), "target \"api\" {\n  dockerfile-inline = <<EOT\nFROM alpine\n\nRUN true\nEOT\n  tags = [\"a\", \"b\"]\n}\n");
 
-                // Add database if specified
-                if let Some(db) = database {
-                    let (db_image, db_port, db_env) = match db.as_str() {
-                        "postgres" => ("postgres:latest", "5432:5432", vec![
-                            ("POSTGRES_USER".to_string(), "admin".to_string()),
-                            ("POSTGRES_PASSWORD".to_string(), "password".to_string()),
-                        ]),
-                        "mysql" => ("mysql:latest", "3306:3306", vec![
-                            ("MYSQL_ROOT_PASSWORD".to_string(), "password".to_string()),
-                            ("MYSQL_DATABASE".to_string(), "app".to_string()),
-                        ]),
-                        "mongodb" => ("mongo:latest", "27017:27017", vec![
-                            ("MONGO_INITDB_ROOT_USERNAME".to_string(), "admin".to_string()),
-                            ("MONGO_INITDB_ROOT_PASSWORD".to_string(), "password".to_string()),
-                        ]),
-                        _ => ("postgres:latest", "5432:5432", vec![
-                            ("POSTGRES_USER".to_string(), "admin".to_string()),
-                            ("POSTGRES_PASSWORD".to_string(), "password".to_string()),
-                        ]),
-                    };
+        let json = "{\n  \"name\": \"Dev Container\",\n  \"runArgs\": [\n    \"--gpus all\"\n  ],\n  \"quote\": \"a \\\" b\"\n}";
+        assert_eq!(minify_json(json), "{\"name\":\"Dev Container\",\"runArgs\":[\"--gpus all\"],\"quote\":\"a \\\" b\"}\n");
 
-                    service_specs.push(ServiceSpec {
-                        name: "db".to_string(),
-                        image: db_image.to_string(),
-                        ports: vec![db_port.to_string()],
-                        depends_on: Vec::new(),
-                        environment: db_env,
-                        volumes: vec!["./data:/var/lib/postgresql/data".to_string()],
-                    });
+        let dir = test_dir("devcontainer-compact");
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = Path::new(&dir).join("devcontainer.json").to_string_lossy().into_owned();
+        run_command(&["configgen", "devcontainer", "-o", &output, "--compact"]);
+        let devcontainer = std::fs::read_to_string(&output).unwrap();
+        assert_eq!(devcontainer.lines().count(), 1, "{}", devcontainer);
+        let value: serde_json::Value = serde_json::from_str(&devcontainer).unwrap();
+        assert_eq!(value["name"], "Dev Container");
+    }
 
-                    // Update main app's depends_on
-                    service_specs[0].depends_on.push("db".to_string());
-                }
+    #[test]
+    fn working_dir_and_hostname_render_per_service() {
+        let compose = compose_output("working-dir-hostname", &["--services", "web,db", "--working-dir", "web:/srv/app", "--hostname", "db:database"]);
+        assert!(compose.contains("    working_dir: /srv/app\n"), "{}", compose);
+        assert!(compose.contains("    hostname: database\n"), "{}", compose);
+        let parsed: serde_yaml::Value = serde_yaml::from_str(&compose).unwrap();
+        let services = &parsed["services"];
+        assert_eq!(services["web"]["working_dir"].as_str(), Some("/srv/app"));
+        assert!(services["web"].get("hostname").is_none());
+        assert_eq!(services["db"]["hostname"].as_str(), Some("database"));
+        assert!(services["db"].get("working_dir").is_none());
 
-                // Add additional services if specified
-                if let Some(additional_services) = services {
-                    for service in additional_services.split(',') {
-                        let service = service.trim();
-                        match service {
-                            "redis" => {
-                                service_specs.push(ServiceSpec {
-                                    name: "redis".to_string(),
-                                    image: "redis:latest".to_string(),
-                                    ports: vec!["6379:6379".to_string()],
-                                    depends_on: Vec::new(),
-                                    environment: Vec::new(),
-                                    volumes: vec!["./redis-data:/data".to_string()],
-                                });
-                                service_specs[0].depends_on.push("redis".to_string());
-                            },
-                            "elasticsearch" => {
-                                service_specs.push(ServiceSpec {
-                                    name: "elasticsearch".to_string(),
-                                    image: "elasticsearch:8.7.0".to_string(),
-                                    ports: vec!["9200:9200".to_string()],
-                                    depends_on: Vec::new(),
-                                    environment: vec![
-                                        ("discovery.type".to_string(), "single-node".to_string()),
-                                        ("ES_JAVA_OPTS".to_string(), "-Xms512m -Xmx512m".to_string()),
-                                    ],
-                                    volumes: vec!["./es-data:/usr/share/elasticsearch/data".to_string()],
-                                });
-                                service_specs[0].depends_on.push("elasticsearch".to_string());
-                            },
-                            _ => (),
-                        }
-                    }
-                }
+        let compose = compose_output("working-dir-hostname-unset", &["--services", "web,db"]);
+        assert!(!compose.contains("working_dir:") && !compose.contains("hostname:"), "{}", compose);
+    }
 
-                let compose_spec = DockerComposeSpec {
-                    services: service_specs,
-                    networks: networks_map,
-                };
-                let compose = render_template(DOCKER_COMPOSE_TEMPLATE, &compose_spec)
-                    .expect("Failed to render docker-compose.yml");
-                write_to_file(&Path::new(&output_dir).join("docker-compose.yml"), &compose)?;
+    #[test]
+    fn init_renders_only_for_selected_services() {
+        let compose = compose_output("init-selected", &["--services", "web,db,worker", "--init", "web,worker"]);
+        let parsed: serde_yaml::Value = serde_yaml::from_str(&compose).unwrap();
+        let services = &parsed["services"];
+        assert_eq!(services["web"]["init"].as_bool(), Some(true));
+        assert_eq!(services["worker"]["init"].as_bool(), Some(true));
+        assert!(services["db"].get("init").is_none(), "{}", compose);
+        assert_eq!(compose.matches("    init: true\n").count(), 2, "{}", compose);
 
-                // 3. Generate devcontainer.json
-                let devcontainer_spec = DevContainerSpec {
-                    name: format!("{} Dev Container", name),
-                    dockerfile_path: "./Dockerfile".to_string(),
-                    remote_user: "vscode".to_string(),
-                    customizations: DevContainerCustomizations {
-                        vscode_extensions: match language.as_str() {
-                            "python" => vec![
-                                "ms-python.python".to_string(),
-                                "ms-python.vscode-pylance".to_string(),
-                            ],
-                            "node" => vec![
-                                "dbaeumer.vscode-eslint".to_string(),
-                                "esbenp.prettier-vscode".to_string(),
-                            ],
-                            "rust" => vec![
-                                "rust-lang.rust-analyzer".to_string(),
-                                "serayuzgur.crates".to_string(),
-                            ],
-                            _ => vec![],
-                        },
-                        settings: json!({
-                            "editor.formatOnSave": true,
-                            "terminal.integrated.shell.linux": "/bin/bash"
-                        }),
-                    },
-                };
-                let devcontainer = render_template(DEVCONTAINER_TEMPLATE, &devcontainer_spec)
-                    .expect("Failed to render devcontainer.json");
-                write_to_file(&Path::new(&output_dir).join("devcontainer.json"), &devcontainer)?;
+        let compose = compose_output("init-unset", &["--services", "web,db"]);
+        assert!(!compose.contains("init:"), "{}", compose);
+    }
 
-                println!("Generated development environment in: {}", output_dir);
-                Ok(())
-            }
-            _ => unreachable!(),
+    #[test]
+    fn bake_no_cache_and_pull_render_when_set() {
+        let target_block = |bake: &str, name: &str| {
+            let start = bake.find(&format!("target \"{}\" {{", name)).unwrap();
+            let end = bake[start..].find("\n}").unwrap();
+            bake[start..start + end].to_string()
+        };
+
+        let bake = bake_output("bake-default-flags", &["--targets", "api,worker"]);
+        assert!(!bake.contains("no-cache") && !bake.contains("pull "), "{}", bake);
+
+        let bake = bake_output("bake-all-flags", &["--targets", "api,worker", "--no-cache", "--pull"]);
+        for name in ["api", "worker"] {
+            let target = target_block(&bake, name);
+            assert!(target.contains("\n  no-cache   = true"), "{}", target);
+            assert!(target.contains("\n  pull       = true"), "{}", target);
         }
+
+        let bake = bake_output("bake-keyed-flags", &["--targets", "api,worker", "--no-cache", "worker", "--pull", "api"]);
+        let (api, worker) = (target_block(&bake, "api"), target_block(&bake, "worker"));
+        assert!(api.contains("pull       = true") && !api.contains("no-cache"), "{}", api);
+        assert!(worker.contains("no-cache   = true") && !worker.contains("pull "), "{}", worker);
+
+        let output = Path::new(&test_dir("bake-unknown-flag")).join("docker-bake.hcl").to_string_lossy().into_owned();
+        let err = try_run_command(&["configgen", "bake", "-o", &output, "--targets", "api", "--pull", "web"]).unwrap_err();
+        assert!(err.to_string().contains("--pull refers to unknown target 'web'"), "{}", err);
     }
-}
 
-fn main() -> io::Result<()> {
-    // Check if any command-line arguments were provided
-    if std::env::args().len() > 1 {
-        // Use the existing CLI parser
-        let cli = Cli::parse();
-        cli.command.execute()
-    } else {
-        // No arguments provided, launch interactive mode
-        interactive_cli()
+    #[test]
+    fn validate_returns_an_error_for_findings() {
+        let dir = test_dir("validate");
+        std::fs::create_dir_all(&dir).unwrap();
+        let dockerfile = Path::new(&dir).join("Dockerfile");
+        std::fs::write(&dockerfile, render_dockerfile(&dockerfile_spec())).unwrap();
+        run_command(&["configgen", "validate", dockerfile.to_str().unwrap()]);
+
+        std::fs::write(&dockerfile, "RUN apt-get update\n").unwrap();
+        let err = try_run_command(&["configgen", "validate", dockerfile.to_str().unwrap()]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("issue(s) found in"), "{}", err);
+    }
+
+    #[test]
+    fn global_entrypoint_mode_reaches_dockerfile_and_init() {
+        let dir = test_dir("entrypoint-mode-global");
+        std::fs::create_dir_all(&dir).unwrap();
+        let dockerfile = Path::new(&dir).join("Dockerfile").to_string_lossy().into_owned();
+        run_command(&["configgen", "--entrypoint-mode", "shell", "dockerfile", "-o", &dockerfile]);
+        let rendered = std::fs::read_to_string(&dockerfile).unwrap();
+        assert!(rendered.lines().any(|line| line == "ENTRYPOINT /bin/bash"), "{}", rendered);
+        run_command(&["configgen", "dockerfile", "-o", &dockerfile]);
+        let rendered = std::fs::read_to_string(&dockerfile).unwrap();
+        assert!(rendered.contains("ENTRYPOINT [\"/bin/bash\"]"), "{}", rendered);
+
+        let dockerfile = init_output("entrypoint-mode-init", &["--entrypoint-mode", "shell", "--language", "node"], "Dockerfile");
+        assert!(dockerfile.lines().any(|line| line == "ENTRYPOINT /bin/bash"), "{}", dockerfile);
     }
 }